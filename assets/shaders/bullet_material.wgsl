@@ -1,22 +1,104 @@
-#import bevy_pbr::forward_io::VertexOutput
+#import bevy_pbr::mesh_view_bindings::view
+
+// See `BulletMaterialUniform`
+struct BulletMaterial {
+    mapping: u32,
+    scale: vec2<f32>,
+    offset: vec2<f32>,
+    tiles: vec2<f32>,
+    tint: vec4<f32>,
+    emissive: vec4<f32>,
+};
+
+// See `UvMapping`
+const WORLD_PLANAR: u32 = 0u;
+const SPHERICAL: u32 = 1u;
+const SCREEN_SPACE: u32 = 2u;
+const RANDOM_TILE: u32 = 3u;
+
+const PI: f32 = 3.14159265;
 
 @group(2) @binding(0) var t: texture_2d<f32>;
 @group(2) @binding(1) var s: sampler;
-@group(2) @binding(2) var<uniform> alpha: f32;
+@group(2) @binding(2) var<uniform> material: BulletMaterial;
+
+struct Vertex {
+    @location(0) position: vec3<f32>,
+
+    // Per bullet, see `BulletInstance`
+    @location(8) i_position_scale: vec4<f32>,
+    @location(9) i_color: vec4<f32>,
+    @location(10) i_uv: vec4<f32>,
+    @location(11) i_seed: u32,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) world_position: vec3<f32>,
+    @location(1) color: vec4<f32>,
+    @location(2) uv_transform: vec4<f32>,
+    @location(3) center: vec3<f32>,
+    @location(4) @interpolate(flat) seed: u32,
+};
+
+@vertex
+fn vertex(vertex: Vertex) -> VertexOutput {
+    let world_position = vertex.position * vertex.i_position_scale.w + vertex.i_position_scale.xyz;
+
+    var out: VertexOutput;
+    out.clip_position = view.view_proj * vec4<f32>(world_position, 1.0);
+    out.world_position = world_position;
+    out.color = vertex.i_color;
+    out.uv_transform = vertex.i_uv;
+    out.center = vertex.i_position_scale.xyz;
+    out.seed = vertex.i_seed;
+    return out;
+}
+
+// Like a globe around the bullet's center
+fn spherical_uv(bullet: VertexOutput) -> vec2<f32> {
+    let direction = normalize(bullet.world_position - bullet.center);
+    return vec2(
+        atan2(direction.z, direction.x) / (2.0 * PI) + 0.5,
+        acos(clamp(direction.y, -1.0, 1.0)) / PI,
+    );
+}
+
+// PCG, scrambles the seed
+fn hash(seed: u32) -> u32 {
+    let state = seed * 747796405u + 2891336453u;
+    let word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+    return (word >> 22u) ^ word;
+}
 
 @fragment
 fn fragment(
     bullet: VertexOutput,
 ) -> @location(0) vec4<f32> {
+    var uv: vec2<f32>;
+    switch material.mapping {
+        case SPHERICAL: {
+            uv = spherical_uv(bullet);
+        }
+        case SCREEN_SPACE: {
+            uv = (bullet.clip_position.xy - view.viewport.xy) / view.viewport.zw;
+        }
+        case RANDOM_TILE: {
+            let tiles = vec2<u32>(material.tiles);
+            let tile = hash(bullet.seed) % (tiles.x * tiles.y);
+            let corner = vec2(f32(tile % tiles.x), f32(tile / tiles.x));
+            uv = (corner + spherical_uv(bullet)) / material.tiles;
+        }
+        default: {
+            uv = bullet.world_position.xz * material.scale + material.offset;
+        }
+    }
+    uv = fract(uv * bullet.uv_transform.zw + bullet.uv_transform.xy);
 
+    let sampled = textureSample(t, s, uv) * material.tint;
 
-    // Offset world pos to outside of circular base
-    var uv = (vec2(5.) + vec2(bullet.world_position.x, bullet.world_position.z)) * 0.4;
-
-    uv.x = uv.x % 1.0;
-    uv.y = uv.y % 1.0;
-
-    let sampled = textureSample(t, s, uv);
-
-    return vec4<f32>(sampled.rgb, alpha);
+    return vec4<f32>(
+        sampled.rgb * bullet.color.rgb + material.emissive.rgb,
+        sampled.a * bullet.color.a,
+    );
 }
@@ -0,0 +1,18 @@
+#import bevy_sprite::mesh2d_vertex_output::VertexOutput
+
+@group(2) @binding(0) var<uniform> backdrop: vec4<f32>;
+@group(2) @binding(1) var color_texture: texture_2d<f32>;
+@group(2) @binding(2) var color_sampler: sampler;
+
+@fragment
+fn fragment(mesh: VertexOutput) -> @location(0) vec4<f32> {
+    // Round, with a soft edge a few percent wide
+    let from_center = length(mesh.uv - vec2<f32>(0.5)) * 2.0;
+    let coverage = 1.0 - smoothstep(0.9, 1.0, from_center);
+
+    let sampled = textureSample(color_texture, color_sampler, mesh.uv);
+    let color = mix(backdrop.rgb, sampled.rgb, sampled.a);
+    let alpha = max(backdrop.a, sampled.a) * coverage;
+
+    return vec4<f32>(color, alpha);
+}
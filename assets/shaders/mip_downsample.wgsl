@@ -0,0 +1,10 @@
+#import bevy_core_pipeline::fullscreen_vertex_shader::FullscreenVertexOutput
+
+@group(0) @binding(0) var bigger_mip: texture_2d<f32>;
+@group(0) @binding(1) var s: sampler;
+
+// Sampling in the middle of the four bigger mip texels with linear filtering averages them
+@fragment
+fn fragment(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
+    return textureSample(bigger_mip, s, in.uv);
+}
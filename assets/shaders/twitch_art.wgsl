@@ -0,0 +1,44 @@
+#import bevy_sprite::mesh2d_vertex_output::VertexOutput
+
+struct TwitchArt {
+    seed: f32,
+    // 0 to 1, loops seamlessly
+    phase: f32,
+    // width / height
+    aspect: f32,
+    // x: ring count, y: swirl, z: hue shift, w: unused
+    params: vec4<f32>,
+}
+
+@group(2) @binding(0) var<uniform> art: TwitchArt;
+
+const TAU: f32 = 6.28318530718;
+
+fn hash(p: vec2<f32>) -> f32 {
+    return fract(sin(dot(p, vec2(127.1, 311.7)) + art.seed * 17.0) * 43758.5453);
+}
+
+// Fox orange to Twitch purple and back
+fn palette(t: f32) -> vec3<f32> {
+    let orange = vec3(0.95, 0.45, 0.1);
+    let purple = vec3(0.57, 0.27, 1.0);
+    return mix(orange, purple, 0.5 + 0.5 * cos(TAU * (t + art.params.z)));
+}
+
+@fragment
+fn fragment(mesh: VertexOutput) -> @location(0) vec4<f32> {
+    var p = (mesh.uv - 0.5) * vec2(art.aspect, 1.0) * 2.0;
+
+    let angle = atan2(p.y, p.x);
+    let radius = length(p);
+
+    // Everything moves with the phase times whole numbers, so phase 1 looks exactly like phase 0
+    let swirl = angle + art.params.y * radius + TAU * art.phase;
+    let rings = sin(radius * art.params.x * TAU - TAU * art.phase + hash(vec2(0.0)) * TAU);
+    let spokes = sin(swirl * (3.0 + floor(hash(vec2(1.0)) * 5.0)));
+
+    let t = 0.5 * rings + 0.25 * spokes + radius * 0.3;
+    let vignette = smoothstep(1.6, 0.2, radius);
+
+    return vec4(palette(t) * vignette, 1.0);
+}
@@ -6,6 +6,12 @@ use bevy::{
     render::{camera::RenderTarget, view::RenderLayers},
 };
 
+use crate::{
+    mip_generation::{GenerateMips, MipGenerationPlugin},
+    render_target_pool::{PooledRenderTarget, RenderTargetPool, RenderTargetPoolPlugin},
+    render_util::RenderTargetBuilder,
+};
+
 pub struct BevyExampleAnimatedFoxPlugin {
     pub resolution: UVec2,
 }
@@ -18,6 +24,13 @@ struct FoxRenderTargetSize(UVec2);
 
 impl Plugin for BevyExampleAnimatedFoxPlugin {
     fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<MipGenerationPlugin>() {
+            app.add_plugins(MipGenerationPlugin);
+        }
+        if !app.is_plugin_added::<RenderTargetPoolPlugin>() {
+            app.add_plugins(RenderTargetPoolPlugin);
+        }
+
         app.insert_resource(FoxRenderTargetSize(self.resolution))
             .add_systems(Startup, setup)
             .add_systems(
@@ -30,8 +43,13 @@ impl Plugin for BevyExampleAnimatedFoxPlugin {
 #[derive(Resource)]
 struct Animations(Vec<Handle<AnimationClip>>);
 
+/// The root of the fox scene
+#[derive(Component)]
+pub struct Fox;
+
+/// The camera rendering the fox into [`FoxRenderTarget`]
 #[derive(Component)]
-struct Fox;
+pub struct FoxCamera;
 
 const FOX_RENDER_LAYER: u8 = 10;
 
@@ -40,6 +58,8 @@ fn setup(
     render_target_size: Res<FoxRenderTargetSize>,
     asset_server: Res<AssetServer>,
     mut images: ResMut<Assets<Image>>,
+    mut generate_mips: ResMut<GenerateMips>,
+    mut pool: ResMut<RenderTargetPool>,
 ) {
     commands.insert_resource(Animations(vec![
         asset_server.load("models/animated/Fox.glb#Animation2"), // Running!
@@ -48,7 +68,15 @@ fn setup(
     // TODO: Consolidate layers
     let fox_layer = RenderLayers::layer(FOX_RENDER_LAYER);
 
-    let target = crate::render_util::make_image(**render_target_size, &mut images);
+    // Mipmapped since the fox is often sampled much smaller than it's rendered
+    let target = pool.lend_with_mips(
+        RenderTargetBuilder::new(**render_target_size)
+            .label("fox_render_target")
+            .readback(),
+        u32::MAX,
+        &mut images,
+        &mut generate_mips,
+    );
 
     // Camera
     commands.spawn((
@@ -57,7 +85,7 @@ fn setup(
                 // Early render
                 // TODO: Consolidate camera render orders
                 order: -100000,
-                target: RenderTarget::Image(target.clone_weak()),
+                target: RenderTarget::Image(target.target.clone()),
                 ..default()
             },
             transform: Transform::from_xyz(100.0, 100.0, 150.0)
@@ -65,8 +93,10 @@ fn setup(
             ..default()
         },
         fox_layer,
+        FoxCamera,
+        PooledRenderTarget(target.target.clone()),
     ));
-    commands.insert_resource(FoxRenderTarget(target));
+    commands.insert_resource(FoxRenderTarget(target.sampled));
 
     commands.spawn((
         DirectionalLightBundle {
@@ -1,38 +1,307 @@
-use std::{f32::consts::TAU, time::Duration};
+use std::{f32::consts::TAU, path::PathBuf, time::Duration};
 
 use bevy::{
+    app::ScheduleRunnerPlugin,
+    input::InputPlugin,
+    log::LogPlugin,
     prelude::*,
-    render::render_resource::{AsBindGroup, ShaderRef},
+    render::camera::ScalingMode,
+    sprite::{MaterialMesh2dBundle, Mesh2dHandle},
+    time::TimeUpdateStrategy,
 };
 
-use streamville::{bevy_example_animated_fox::FoxRenderTarget, prelude::*};
+use streamville::{
+    bevy_example_animated_fox::FoxRenderTarget,
+    instanced_bullets::{
+        BulletInstance, BulletInstances, BulletInstancesBundle, BulletMaterial, UvMapping,
+    },
+    prelude::*,
+};
+
+const TICK: Duration = Duration::from_millis(5);
 
+/// A bullet-hell run with the fox as the player, see [`BulletGamePlugin`].
+///
+/// # Usage
+///
+/// ```text
+/// bullet-hell [patterns/spiral.pattern.ron | levels/first.level.ron] [--seed 0]
+///             [--record replays/run.ron | --play replays/run.ron | --verify replays/run.ron]
+///             [--benchmark benchmarks/bullet-hell.csv] [--2d]
+/// ```
+///
+/// Fires the pattern, relative to `assets/`, from the center, plays the level's waves, see
+/// [`LevelPlugin`], or without either, fights a boss which grazing damages. Runs are deterministic, see [`SimulationPlugin`]:
+/// `--record` saves one as a replay on exit, `--play` plays it back with the pattern and seed it
+/// was recorded with, and `--verify` does so headless, as fast as it can, and exits with an error
+/// if it doesn't end up the same.
+///
+/// `--benchmark` fills the arena with more and more bullets instead, and writes how the frame
+/// and fixed update times keep up to the CSV, see [`BulletBenchmarkPlugin`].
+///
+/// `--2d` plays top-down in a small overlay in the corner instead, with the fox only as a
+/// texture, on bullets and on the player. It's the same simulation, so replays play in either.
 fn main() {
-    App::new()
-        .add_plugins(DefaultPlugins.with_transparent_window_at(IVec2::new(1800, 200)))
-        .insert_resource(ClearColor(Color::NONE))
-        .insert_resource(Time::<Fixed>::from_duration(Duration::from_millis(5)))
+    let args = Args::parse();
+
+    let mut app = App::new();
+    if args.headless {
+        app.add_plugins((
+            MinimalPlugins.set(ScheduleRunnerPlugin::run_loop(Duration::ZERO)),
+            LogPlugin::default(),
+            AssetPlugin::default(),
+            InputPlugin,
+        ))
+        .insert_resource(TimeUpdateStrategy::ManualDuration(TICK));
+    } else if args.two_d {
+        add_presentation_2d(&mut app);
+    } else {
+        add_presentation(&mut app);
+    }
+
+    app.insert_resource(Time::<Fixed>::from_duration(TICK))
         .add_plugins((
-            WorldAxesGizmoPlugin,
-            BevyExampleAnimatedFoxPlugin {
-                resolution: UVec2 { x: 2048, y: 2048 },
-            },
-            MaterialPlugin::<BulletMaterial>::default(),
+            BulletPatternPlugin,
+            BulletsPlugin,
+            BulletBehaviorsPlugin,
+            BulletGamePlugin,
+            BossPlugin,
+            LevelPlugin,
+            SimulationPlugin { seed: args.seed },
         ))
-        .add_systems(Startup, setup)
-        .add_systems(FixedUpdate, (spiral_spawner, print_num_bullets))
+        .insert_resource(BulletArena {
+            plane: if args.two_d {
+                ArenaPlane::XY
+            } else {
+                ArenaPlane::XZ
+            },
+            bounds: ArenaBounds::Circle {
+                center: Vec2::ZERO,
+                radius: FADE_RADIUS,
+            },
+            ..default()
+        })
+        .insert_resource(PatternPath(args.pattern))
         .add_systems(
-            Update,
-            (rotate_camera, move_bullets, fade, destroy.after(fade)),
-        )
-        .run();
+            FixedUpdate,
+            damage_boss_on_graze
+                .in_set(SimulationSet)
+                .after(GameSet::Collide),
+        );
+
+    if let Some(replay) = args.replay {
+        app.add_plugins(replay);
+    }
+    let replay_result = app.world.get_resource::<ReplayResult>().cloned();
+
+    // Nothing to fire at or dodge, only bullets
+    match args.benchmark {
+        Some(path) => {
+            app.add_plugins(BulletBenchmarkPlugin { path, ..default() });
+        }
+        None => {
+            app.add_systems(Startup, setup_simulation);
+        }
+    }
+
+    app.run();
+
+    if replay_result.is_some_and(|result| result.matches() != Some(true)) {
+        std::process::exit(1);
+    }
+}
+
+/// Everything but the simulation
+fn add_presentation(app: &mut App) {
+    app.add_plugins(
+        DefaultPlugins.with_primary_window(
+            WindowPreset::overlay()
+                .draggable()
+                .anchor(OverlayAnchor::TopRight)
+                .margin(0.05)
+                .size(Vec2::new(0.3, 0.4)),
+        ),
+    )
+    .insert_resource(ClearColor(Color::NONE))
+    .add_plugins((
+        WorldAxesGizmoPlugin,
+        OfflineRenderPlugin,
+        BevyExampleAnimatedFoxPlugin {
+            resolution: UVec2 { x: 2048, y: 2048 },
+        },
+        InstancedBulletsPlugin,
+        PngCapturePlugin,
+        WindowLayoutPlugin::default(),
+    ))
+    .add_systems(Startup, setup)
+    .add_systems(
+        Update,
+        (capture_fox_on_hotkey, spawn_bullet_renderer).run_if(resource_added::<FoxRenderTarget>),
+    )
+    .add_systems(
+        Update,
+        (
+            add_fox_models,
+            rotate_camera,
+            cycle_uv_mapping,
+            draw_bullets,
+            face_movement,
+            blink_while_invulnerable,
+            show_hitbox,
+            update_hud,
+            apply_level_cues,
+            move_camera,
+        ),
+    );
+}
+
+/// Everything but the simulation, in 2D
+fn add_presentation_2d(app: &mut App) {
+    app.add_plugins(
+        DefaultPlugins.with_primary_window(
+            WindowPreset::overlay()
+                .draggable()
+                .anchor(OverlayAnchor::BottomRight)
+                .margin(0.02)
+                .size(Vec2::new(0.18, 0.3)),
+        ),
+    )
+    .insert_resource(ClearColor(Color::NONE))
+    .add_plugins((
+        OfflineRenderPlugin,
+        BevyExampleAnimatedFoxPlugin {
+            resolution: UVec2::splat(512),
+        },
+        SpriteBulletsPlugin,
+        PngCapturePlugin,
+        WindowLayoutPlugin {
+            path: PathBuf::from("window-layouts/bullet-hell-2d.ron"),
+            ..default()
+        },
+    ))
+    .add_systems(Startup, setup_2d)
+    .add_systems(
+        Update,
+        (capture_fox_on_hotkey, spawn_bullet_sprites).run_if(resource_added::<FoxRenderTarget>),
+    )
+    .add_systems(
+        Update,
+        (
+            add_fox_sprites,
+            blink_while_invulnerable,
+            show_hitbox,
+            update_hud,
+            apply_level_cues,
+        ),
+    );
+}
+
+/// Pattern to fire or level to play, relative to `assets/`, or `None` for the boss
+#[derive(Debug, Resource)]
+struct PatternPath(Option<String>);
+
+struct Args {
+    pattern: Option<String>,
+    seed: u64,
+    replay: Option<ReplayPlugin>,
+    headless: bool,
+    benchmark: Option<PathBuf>,
+    two_d: bool,
+}
+
+impl Args {
+    fn parse() -> Self {
+        let mut pattern = None;
+        let mut seed = 0;
+        let mut record = None;
+        let mut play = None;
+        let mut headless = false;
+        let mut benchmark = None;
+        let mut two_d = false;
+
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            if !arg.starts_with("--") {
+                pattern = Some(arg);
+                continue;
+            }
+            if arg == "--2d" {
+                two_d = true;
+                continue;
+            }
+
+            let value = args.next().unwrap_or_else(|| panic!("{arg} needs a value"));
+            match arg.as_str() {
+                "--seed" => {
+                    seed = value
+                        .parse()
+                        .unwrap_or_else(|_| panic!("{arg} needs a number, got {value}"))
+                }
+                "--record" => record = Some(PathBuf::from(value)),
+                "--play" | "--verify" => {
+                    headless = arg == "--verify";
+                    play = Some(
+                        Replay::load(value.as_ref())
+                            .unwrap_or_else(|e| panic!("Could not load replay {value}: {e}")),
+                    );
+                }
+                "--benchmark" => benchmark = Some(PathBuf::from(value)),
+                _ => panic!("Unknown argument {arg}"),
+            }
+        }
+
+        if benchmark.is_some() && (record.is_some() || play.is_some()) {
+            panic!("Either benchmark or record or play a replay, not both");
+        }
+
+        let replay = match (record, play) {
+            (Some(_), Some(_)) => panic!("Either record or play a replay, not both"),
+            (Some(path), None) => Some(ReplayPlugin::Record {
+                path,
+                settings: pattern
+                    .iter()
+                    .map(|pattern| ("pattern".to_owned(), pattern.clone()))
+                    .collect(),
+            }),
+            (None, Some(replay)) => {
+                seed = replay.seed;
+                pattern = replay.settings.get("pattern").cloned();
+                Some(if headless {
+                    ReplayPlugin::Verify(replay)
+                } else {
+                    ReplayPlugin::Play(replay)
+                })
+            }
+            (None, None) => None,
+        };
+
+        Self {
+            pattern,
+            seed,
+            replay,
+            headless,
+            benchmark,
+            two_d,
+        }
+    }
 }
 
 #[derive(Debug, Component)]
 struct MainCamara;
 
-fn rotate_camera(time: Res<Time>, mut camera: Query<&mut Transform, With<MainCamara>>) {
-    let mut cam_transform = camera.single_mut();
+fn capture_fox_on_hotkey(fox: Res<FoxRenderTarget>, mut settings: ResMut<PngCaptureSettings>) {
+    settings
+        .hotkey_targets
+        .push(("fox".to_owned(), CaptureTarget::Image(fox.clone())));
+}
+
+fn rotate_camera(
+    time: Res<Time>,
+    mut camera: Query<&mut Transform, (With<MainCamara>, Without<CameraTween>)>,
+) {
+    let Ok(mut cam_transform) = camera.get_single_mut() else {
+        return;
+    };
 
     cam_transform.rotate_around(
         Vec3::ZERO,
@@ -40,91 +309,456 @@ fn rotate_camera(time: Res<Time>, mut camera: Query<&mut Transform, With<MainCam
     );
 }
 
-fn move_bullets(t: Res<Time>, mut bullets: Query<(&mut Transform, &Heading)>) {
-    let speed = 0.8;
-
-    for (mut bullet_transform, heading) in &mut bullets {
-        bullet_transform.translation += **heading * t.delta_seconds() * speed;
-    }
+/// All bullets are drawn at once, by this
+fn spawn_bullet_renderer(
+    fox_texture: Res<FoxRenderTarget>,
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<BulletMaterial>>,
+) {
+    commands.spawn(BulletInstancesBundle::new(
+        meshes.add(Sphere::default()),
+        materials.add(BulletMaterial {
+            color_texture: Some(fox_texture.clone_weak()),
+            ..default()
+        }),
+    ));
 }
 
-fn print_num_bullets(bullets: Query<Entity, With<Bullet>>) {
-    debug!("Bullets: {}", bullets.iter().count());
+/// Bullets are round sprites of the fox in 2D
+fn spawn_bullet_sprites(
+    fox_texture: Res<FoxRenderTarget>,
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<BulletSpriteMaterial>>,
+) {
+    commands.insert_resource(BulletSprites {
+        mesh: meshes.add(Rectangle::default()).into(),
+        material: materials.add(BulletSpriteMaterial {
+            backdrop: Color::rgba(1.0, 0.55, 0.2, 0.6),
+            texture: Some(fox_texture.clone_weak()),
+        }),
+        size: BULLET_SPRITE_SIZE,
+        z: 1.0,
+    });
 }
 
-/// Destroy when fully transparent
-fn destroy(
-    mut commands: Commands,
+/// Ways to map the fox onto bullets, cycled through with U
+const UV_MAPPINGS: [UvMapping; 4] = [
+    UvMapping::WorldPlanar {
+        scale: Vec2::splat(0.4),
+        offset: Vec2::splat(2.0),
+    },
+    UvMapping::Spherical,
+    UvMapping::ScreenSpace,
+    UvMapping::RandomTile {
+        columns: 4,
+        rows: 4,
+    },
+];
 
-    bullets: Query<(Entity, &Handle<BulletMaterial>), With<Bullet>>,
-    materials: Res<Assets<BulletMaterial>>,
+fn cycle_uv_mapping(
+    keys: Res<ButtonInput<KeyCode>>,
+    renderer: Query<&BulletInstances>,
+    mut materials: ResMut<Assets<BulletMaterial>>,
+    mut current: Local<usize>,
 ) {
-    for (entity, material_handle) in &bullets {
-        let Some(material) = materials.get(material_handle) else {
+    if !keys.just_pressed(KeyCode::KeyU) {
+        return;
+    }
+
+    *current = (*current + 1) % UV_MAPPINGS.len();
+    for renderer in &renderer {
+        let Some(material) = materials.get_mut(&renderer.material) else {
             continue;
         };
+        material.mapping = UV_MAPPINGS[*current];
+        // Random tiles are hard to tell apart without a glow
+        material.emissive = match UV_MAPPINGS[*current] {
+            UvMapping::RandomTile { .. } => Color::rgb(0.1, 0.05, 0.0),
+            _ => Color::BLACK,
+        };
+    }
+    info!("Bullet UV mapping: {:?}", UV_MAPPINGS[*current]);
+}
+
+/// Bullets fly this high above the base, where the emitter is
+const BULLET_HEIGHT: f32 = 0.5;
+
+const BULLET_SIZE: f32 = 0.1;
+
+/// Larger in 2D, to make out the fox
+const BULLET_SPRITE_SIZE: f32 = 0.16;
+
+/// Bullets fade out towards the edge of the base
+const FADE_RADIUS: f32 = 3.5;
+
+fn draw_bullets(
+    arena: Res<BulletArena>,
+    bullets: Query<(Entity, &Bullet)>,
+    mut renderer: Query<&mut BulletInstances>,
+) {
+    let Ok(mut renderer) = renderer.get_single_mut() else {
+        return;
+    };
+
+    renderer.instances.clear();
+    renderer
+        .instances
+        .extend(bullets.iter().map(|(entity, bullet)| BulletInstance {
+            position: arena.plane.unproject(bullet.position, BULLET_HEIGHT),
+            scale: BULLET_SIZE,
+            color: [
+                1.0,
+                1.0,
+                1.0,
+                (FADE_RADIUS - bullet.position.length()).min(1.),
+            ],
+            seed: entity.index(),
+            ..default()
+        }));
+}
+
+/// The fox model is huge
+const FOX_SCALE: f32 = 0.003;
+
+/// Shows lives and score
+#[derive(Debug, Component)]
+struct Hud;
 
-        if material.alpha <= 0.0 {
-            commands.entity(entity).despawn_recursive();
+/// The fox model faces +Z, and looking points -Z at the target
+fn face_movement(arena: Res<BulletArena>, mut players: Query<(&mut Transform, &Player)>) {
+    for (mut transform, player) in &mut players {
+        if player.velocity.length_squared() < 0.01 {
+            continue;
         }
+
+        let heading = arena.plane.unproject(player.velocity.normalize(), 0.0);
+        transform.look_to(-heading, Vec3::Y);
     }
 }
 
-/// Fade based on distance
-fn fade(
-    bullets: Query<(&Transform, &Handle<BulletMaterial>), With<Bullet>>,
-    mut materials: ResMut<Assets<BulletMaterial>>,
+fn blink_while_invulnerable(
+    time: Res<Time>,
+    game: Res<BulletGame>,
+    mut players: Query<&mut Visibility, With<Player>>,
 ) {
-    for (Transform { translation, .. }, material_handle) in &bullets {
-        let Some(material) = materials.get_mut(material_handle) else {
+    let visible = game.invulnerable <= 0.0 || (time.elapsed_seconds() * 5.0).fract() < 0.5;
+
+    for mut visibility in &mut players {
+        visibility.set_if_neq(if visible {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        });
+    }
+}
+
+/// Where bullets actually hit, while focused
+fn show_hitbox(
+    mut gizmos: Gizmos,
+    arena: Res<BulletArena>,
+    params: Res<BulletGameParams>,
+    players: Query<&Player>,
+) {
+    for player in &players {
+        if !player.focused {
             continue;
-        };
+        }
 
-        let dist = (3.5 - translation.length()).min(1.);
-        material.alpha = dist;
+        let position = arena.plane.unproject(player.position, BULLET_HEIGHT);
+        let normal = match arena.plane {
+            ArenaPlane::XZ => Direction3d::Y,
+            ArenaPlane::XY => Direction3d::Z,
+        };
+        gizmos.circle(position, normal, params.hitbox_radius, Color::WHITE);
+        gizmos.circle(position, normal, params.graze_radius, Color::CYAN);
     }
 }
 
-/// It's a bullet!
-#[derive(Debug, Clone, Copy, Component)]
-struct Bullet;
+fn update_hud(
+    game: Res<BulletGame>,
+    levels: Res<Assets<Level>>,
+    bosses: Query<&Boss>,
+    runners: Query<&LevelRunner>,
+    mut hud: Query<&mut Text, With<Hud>>,
+) {
+    let mut text = match game.phase {
+        GamePhase::Playing => format!(
+            "Lives {}   Score {}   Grazes {}",
+            game.lives, game.score, game.grazes
+        ),
+        GamePhase::GameOver => format!("Game over! Score {}, press R to restart", game.score),
+    };
 
-/// Direction headed
-#[derive(Debug, Clone, Copy, Deref, DerefMut, Component)]
-struct Heading(Direction3d);
+    for boss in &bosses {
+        text += &if boss.defeated() {
+            "\nBoss defeated!".to_owned()
+        } else {
+            format!(
+                "\nBoss phase {}/{}   {:.0}%",
+                boss.phase + 1,
+                boss.phases.len(),
+                boss.health / boss.max_health * 100.0
+            )
+        };
+    }
 
-/// Spawns spheres (bullets) in a spiral fashion
-fn spiral_spawner(
-    fox_texture: Res<FoxRenderTarget>,
-    mut counter: Local<usize>,
-    mut commands: Commands,
+    for runner in &runners {
+        if let Some(wave) = runner.current(&levels) {
+            text += &format!(
+                "\nWave {} {}{}",
+                runner.wave + 1,
+                wave.name,
+                if runner.loop_wave { " (looping)" } else { "" }
+            );
+        } else if runner.finished(&levels) {
+            text += "\nLevel cleared!";
+        }
+    }
 
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<BulletMaterial>>,
-) {
-    const BULLETS_PER_REVOLUTION: f32 = 16.0;
-    let n = *counter as f32 / BULLETS_PER_REVOLUTION;
+    for mut hud in &mut hud {
+        if hud.sections[0].value != text {
+            hud.sections[0].value = text.clone();
+        }
+    }
+}
+
+/// Grazing is how the player fights back
+fn damage_boss_on_graze(mut events: EventReader<BulletGameEvent>, mut bosses: Query<&mut Boss>) {
+    let grazes = events
+        .read()
+        .filter(|event| **event == BulletGameEvent::Graze)
+        .count();
+    if grazes == 0 {
+        return;
+    }
+
+    for mut boss in &mut bosses {
+        boss.damage(grazes as f32 * BOSS_GRAZE_DAMAGE);
+    }
+}
 
-    let heading =
-        Direction3d::new(Vec3::new(n.cos(), 0.0, n.sin())).expect("should be non-zero length 1");
+const BOSS_HEALTH: f32 = 100.0;
+const BOSS_GRAZE_DAMAGE: f32 = 1.0;
+const BOSS_SCALE: f32 = 0.006;
 
-    let material: BulletMaterial = BulletMaterial {
-        color_texture: Some(fox_texture.clone_weak()),
-        alpha: 1.0,
+/// Three phases, each ending early if grazed enough
+fn boss_phases(asset_server: &AssetServer) -> Vec<BossPhase> {
+    let pattern = |path: &str| asset_server.load(format!("patterns/{path}.pattern.ron"));
+    let orbiter = |angle| {
+        PhaseEmitter::new(pattern("orbit")).path(ArenaPath::Circle {
+            center: Vec2::ZERO,
+            radius: 0.6,
+            period: 4.0,
+            angle,
+        })
     };
 
+    vec![
+        BossPhase {
+            ends: PhaseEnd::AfterOrHealthBelow(30.0, 0.7),
+            emitters: vec![PhaseEmitter::new(pattern("spiral"))],
+            path: Some(ArenaPath::Circle {
+                center: Vec2::ZERO,
+                radius: 0.8,
+                period: 12.0,
+                angle: 0.0,
+            }),
+        },
+        BossPhase {
+            ends: PhaseEnd::AfterOrHealthBelow(40.0, 0.35),
+            emitters: vec![
+                orbiter(0.0),
+                orbiter(120.0),
+                orbiter(240.0),
+                PhaseEmitter::new(pattern("behaviors")),
+            ],
+            path: Some(ArenaPath::Line {
+                from: Vec2::new(-1.5, -1.0),
+                to: Vec2::new(1.5, -1.0),
+                duration: 8.0,
+            }),
+        },
+        BossPhase {
+            ends: PhaseEnd::HealthBelow(0.01),
+            emitters: vec![
+                PhaseEmitter::new(pattern("flower")),
+                PhaseEmitter::new(pattern("behaviors")).offset(Vec2::new(0.0, -0.3)),
+            ],
+            path: Some(ArenaPath::spline(
+                &[
+                    Vec2::new(0.0, -1.5),
+                    Vec2::new(1.5, 0.0),
+                    Vec2::new(0.0, 0.5),
+                    Vec2::new(-1.5, 0.0),
+                ],
+                3.0,
+            )),
+        },
+    ]
+}
+
+/// The same headless or not
+fn setup_simulation(
+    pattern: Res<PatternPath>,
+    params: Res<BulletGameParams>,
+    arena: Res<BulletArena>,
+    asset_server: Res<AssetServer>,
+    mut commands: Commands,
+) {
+    let above_center =
+        Transform::from_translation(arena.plane.unproject(Vec2::ZERO, BULLET_HEIGHT));
+
+    match &pattern.0 {
+        Some(level) if level.ends_with(".level.ron") => {
+            commands.spawn((
+                TransformBundle::from_transform(above_center),
+                LevelRunner::new(asset_server.load(level)),
+            ));
+        }
+        // bullet emitter, above the center
+        Some(pattern) => {
+            commands.spawn((
+                TransformBundle::from_transform(above_center),
+                BulletEmitter::new(asset_server.load(pattern)),
+            ));
+        }
+        None => {
+            commands.spawn((
+                SpatialBundle::from_transform(above_center),
+                Boss::new(BOSS_HEALTH, boss_phases(&asset_server)),
+            ));
+        }
+    }
+
     commands.spawn((
-        MaterialMeshBundle {
-            mesh: meshes.add(Sphere::default()),
-            material: materials.add(material),
-            transform: Transform::from_xyz(0.0, 0.5, 0.0).with_scale(Vec3::splat(0.1)),
+        SpatialBundle::default(),
+        Player {
+            position: params.start,
             ..default()
         },
-        Heading(heading),
-        Bullet,
     ));
+}
+
+/// Moving to where a wave wants the camera, instead of rotating
+#[derive(Debug, Component)]
+struct CameraTween {
+    from: Transform,
+    to: Transform,
+    elapsed: f32,
+    seconds: f32,
+}
+
+/// The level's music, replaced when a wave cues other music
+#[derive(Debug, Component)]
+struct Music;
+
+fn apply_level_cues(
+    mut events: EventReader<LevelEvent>,
+    levels: Res<Assets<Level>>,
+    runners: Query<&LevelRunner>,
+    asset_server: Res<AssetServer>,
+    mut commands: Commands,
+    mut clear_color: ResMut<ClearColor>,
+    music: Query<Entity, With<Music>>,
+    camera: Query<(Entity, &Transform), With<MainCamara>>,
+) {
+    for event in events.read() {
+        let LevelEvent::WaveStarted { runner, wave } = *event else {
+            continue;
+        };
+        let Some(wave) = runners
+            .get(runner)
+            .ok()
+            .and_then(|runner| levels.get(&runner.level))
+            .and_then(|level| level.waves.get(wave))
+        else {
+            continue;
+        };
+
+        if let Some(background) = wave.background {
+            clear_color.0 = background;
+        }
 
-    *counter += 1;
+        if let Some(path) = &wave.music {
+            for music in &music {
+                commands.entity(music).despawn();
+            }
+            commands.spawn((
+                AudioBundle {
+                    source: asset_server.load(path),
+                    settings: PlaybackSettings::LOOP,
+                },
+                Music,
+            ));
+        }
+
+        if let Some(camera_move) = wave.camera {
+            for (entity, transform) in &camera {
+                commands.entity(entity).insert(CameraTween {
+                    from: *transform,
+                    to: Transform::from_translation(camera_move.position)
+                        .looking_at(camera_move.look_at, Vec3::Y),
+                    elapsed: 0.0,
+                    seconds: camera_move.seconds,
+                });
+            }
+        }
+    }
+}
+
+fn move_camera(
+    time: Res<Time>,
+    mut commands: Commands,
+    mut camera: Query<(Entity, &mut Transform, &mut CameraTween)>,
+) {
+    for (entity, mut transform, mut tween) in &mut camera {
+        tween.elapsed += time.delta_seconds();
+
+        let t = if tween.seconds > 0.0 {
+            (tween.elapsed / tween.seconds).min(1.0)
+        } else {
+            1.0
+        };
+        // Ease in and out
+        let t = t * t * (3.0 - 2.0 * t);
+
+        transform.translation = tween.from.translation.lerp(tween.to.translation, t);
+        transform.rotation = tween.from.rotation.slerp(tween.to.rotation, t);
+
+        if tween.elapsed >= tween.seconds {
+            commands.entity(entity).remove::<CameraTween>();
+        }
+    }
+}
+
+/// The player is a fox, and so is the boss, only bigger and standing on the base
+fn add_fox_models(
+    asset_server: Res<AssetServer>,
+    mut commands: Commands,
+    players: Query<Entity, Added<Player>>,
+    bosses: Query<Entity, Added<Boss>>,
+) {
+    let models = players
+        .iter()
+        .map(|player| (player, Transform::from_scale(Vec3::splat(FOX_SCALE))))
+        .chain(bosses.iter().map(|boss| {
+            (
+                boss,
+                Transform::from_xyz(0.0, -BULLET_HEIGHT, 0.0).with_scale(Vec3::splat(BOSS_SCALE)),
+            )
+        }));
+
+    for (entity, transform) in models {
+        commands.entity(entity).with_children(|parent| {
+            parent.spawn(SceneBundle {
+                scene: asset_server.load("models/animated/Fox.glb#Scene0"),
+                transform,
+                ..default()
+            });
+        });
+    }
 }
 
 fn setup(
@@ -141,6 +775,8 @@ fn setup(
         ..default()
     });
 
+    spawn_hud(&mut commands, 24.0);
+
     // light
     commands.spawn(PointLightBundle {
         point_light: PointLight { ..default() },
@@ -157,27 +793,73 @@ fn setup(
             ..default()
         },
         MainCamara,
+        ArenaCamera,
+    ));
+}
+
+fn spawn_hud(commands: &mut Commands, font_size: f32) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size,
+                color: Color::WHITE,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            left: Val::Px(font_size / 2.0),
+            bottom: Val::Px(font_size / 2.0),
+            ..default()
+        }),
+        Hud,
     ));
 }
 
-#[derive(Asset, TypePath, AsBindGroup, Debug, Clone)]
-struct BulletMaterial {
-    #[texture(0)]
-    #[sampler(1)]
-    color_texture: Option<Handle<Image>>,
+/// The arena fills the window's height, seen from above
+fn setup_2d(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    // circular base
+    commands.spawn(MaterialMesh2dBundle {
+        mesh: Mesh2dHandle(meshes.add(Circle::new(FADE_RADIUS))),
+        material: materials.add(Color::LIME_GREEN.with_a(0.5)),
+        ..default()
+    });
 
-    #[uniform(2)]
-    alpha: f32,
+    spawn_hud(&mut commands, 14.0);
+
+    let mut camera = Camera2dBundle::default();
+    camera.projection.scaling_mode = ScalingMode::FixedVertical(FADE_RADIUS * 2.0 + 0.2);
+    commands.spawn((camera, ArenaCamera));
 }
 
-/// The Material trait is very configurable, but comes with sensible defaults for all methods.
-/// You only need to implement functions for features that need non-default behavior. See the Material api docs for details!
-impl Material for BulletMaterial {
-    fn fragment_shader() -> ShaderRef {
-        "shaders/bullet_material.wgsl".into()
-    }
+/// The player's fox is a sprite in 2D, and so is the boss, only bigger and below bullets
+fn add_fox_sprites(
+    fox_texture: Res<FoxRenderTarget>,
+    mut commands: Commands,
+    players: Query<Entity, Added<Player>>,
+    bosses: Query<Entity, Added<Boss>>,
+) {
+    let sprites = players
+        .iter()
+        .map(|player| (player, 0.5, 2.0))
+        .chain(bosses.iter().map(|boss| (boss, 1.2, 0.0)));
 
-    fn alpha_mode(&self) -> AlphaMode {
-        AlphaMode::Blend
+    for (entity, size, z) in sprites {
+        commands.entity(entity).with_children(|parent| {
+            parent.spawn(SpriteBundle {
+                texture: fox_texture.clone(),
+                sprite: Sprite {
+                    custom_size: Some(Vec2::splat(size)),
+                    ..default()
+                },
+                transform: Transform::from_xyz(0.0, 0.0, z),
+                ..default()
+            });
+        });
     }
 }
@@ -0,0 +1,114 @@
+/// Checks that click-through works on X11, by reading back the window's input region.
+///
+/// Exits with an error if the region isn't what [`ClickThrough`] asked for.
+/// Runs fine under Xvfb, e.g. `xvfb-run -a cargo run --bin click-through-check`.
+#[cfg(target_os = "linux")]
+fn main() {
+    check::main();
+}
+
+#[cfg(not(target_os = "linux"))]
+fn main() {
+    eprintln!("Click-through is only checked on X11");
+}
+
+#[cfg(target_os = "linux")]
+mod check {
+    use bevy::{app::AppExit, prelude::*, window::RawHandleWrapper};
+    use raw_window_handle::RawWindowHandle;
+    use x11rb::rust_connection::RustConnection;
+
+    use streamville::{click_through::x11::input_region, prelude::*};
+
+    pub fn main() {
+        App::new()
+            .add_plugins(
+                DefaultPlugins
+                    .set(WindowPlugin {
+                        primary_window: Some(Window {
+                            resolution: WINDOW_SIZE.into(),
+                            ..default()
+                        }),
+                        ..default()
+                    })
+                    .with_primary_window(
+                        WindowPreset::overlay()
+                            .click_through()
+                            .scale_factor_override(1.0),
+                    ),
+            )
+            .insert_resource(Checks {
+                connection: x11rb::connect(None).expect("needs an X server").0,
+                step: 0,
+            })
+            .add_systems(Startup, make_button_interactive)
+            .add_systems(Update, check_input_region)
+            .run();
+    }
+
+    const WINDOW_SIZE: (f32, f32) = (400.0, 300.0);
+
+    /// Stays clickable, in logical pixels
+    const BUTTON: Rect = Rect {
+        min: Vec2::new(10.0, 20.0),
+        max: Vec2::new(110.0, 70.0),
+    };
+
+    /// Frames between changing the click-through and checking it, so it's surely applied
+    const SETTLE_FRAMES: u32 = 10;
+
+    #[derive(Resource)]
+    struct Checks {
+        connection: RustConnection,
+        step: u32,
+    }
+
+    fn make_button_interactive(mut windows: Query<&mut ClickThrough>) {
+        windows.single_mut().interactive = vec![BUTTON];
+    }
+
+    fn check_input_region(
+        mut commands: Commands,
+        mut checks: ResMut<Checks>,
+        windows: Query<(Entity, &RawHandleWrapper)>,
+        mut exit: EventWriter<AppExit>,
+    ) {
+        let Ok((entity, handle)) = windows.get_single() else {
+            return;
+        };
+
+        let window = match handle.window_handle {
+            RawWindowHandle::Xlib(handle) => handle.window as u32,
+            RawWindowHandle::Xcb(handle) => handle.window.get(),
+            _ => panic!("Not an X11 window"),
+        };
+
+        checks.step += 1;
+
+        let region = || input_region(&checks.connection, window).expect("query input region");
+
+        if checks.step == SETTLE_FRAMES {
+            let expected = vec![IRect::from_corners(
+                BUTTON.min.as_ivec2(),
+                BUTTON.max.as_ivec2(),
+            )];
+            expect("Only the button is interactive", region(), expected);
+
+            commands.entity(entity).remove::<ClickThrough>();
+        } else if checks.step == 2 * SETTLE_FRAMES {
+            let expected = vec![IRect::new(0, 0, WINDOW_SIZE.0 as i32, WINDOW_SIZE.1 as i32)];
+            expect("The whole window is interactive again", region(), expected);
+
+            info!("Click-through works");
+            exit.send(AppExit);
+        }
+    }
+
+    fn expect(what: &str, actual: Vec<IRect>, expected: Vec<IRect>) {
+        if actual != expected {
+            error!("{what}: Expected input region {expected:?}, got {actual:?}");
+            std::process::exit(1);
+        }
+        info!("{what}: {actual:?}");
+    }
+}
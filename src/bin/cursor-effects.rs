@@ -6,17 +6,26 @@ use bevy::{
     sprite::{Material2d, Material2dPlugin, MaterialMesh2dBundle},
     window::close_on_esc,
 };
-use streamville::prelude::*;
+use streamville::{
+    bevy_example_animated_fox::{Fox, FoxCamera, FoxRenderTarget},
+    prelude::*,
+};
 
 const DEBUG: bool = false;
 
 fn main() {
     App::new()
-        .add_plugins(DefaultPlugins.with_transparent_fullscreen_window())
+        .add_plugins(DefaultPlugins.with_primary_window(WindowPreset::transparent_fullscreen()))
         .insert_resource(ClearColor(Color::NONE))
         .add_plugins((
             WorldAxesGizmoPlugin,
+            OfflineRenderPlugin,
             Material2dPlugin::<MouseMaterial>::default(),
+            BevyExampleAnimatedFoxPlugin {
+                resolution: UVec2::splat(FOX_SIZE as u32),
+            },
+            FoxLookAtPlugin,
+            GlobalCursorPlugin,
         ))
         .add_systems(Startup, setup)
         .add_systems(
@@ -25,6 +34,8 @@ fn main() {
                 close_on_esc,
                 spawn_on_move,
                 despawn,
+                show_fox.run_if(resource_added::<FoxRenderTarget>),
+                fox_looks_at_cursor,
                 debug_2d_positions.run_if(|| DEBUG),
             ),
         )
@@ -47,6 +58,55 @@ fn setup(mut commands: Commands) {
     commands.spawn((Camera2dBundle::default(), MainCamara));
 }
 
+/// Size of the fox in the bottom right corner, in pixels
+const FOX_SIZE: f32 = 512.0;
+
+fn show_fox(
+    mut commands: Commands,
+    window: Query<&Window>,
+    fox_texture: Res<FoxRenderTarget>,
+    fox_camera: Query<Entity, With<FoxCamera>>,
+) {
+    let window = window.single();
+
+    // Window coordinates have the origin in the top left, 2D world coordinates in the center
+    let top_left = Vec2::new(window.width() - FOX_SIZE, window.height() - FOX_SIZE);
+    let center = top_left + Vec2::splat(FOX_SIZE / 2.);
+    let translation = Vec3::new(
+        center.x - window.width() / 2.,
+        -center.y + window.height() / 2.,
+        0.0,
+    );
+
+    commands.spawn(SpriteBundle {
+        texture: fox_texture.clone(),
+        sprite: Sprite {
+            custom_size: Some(Vec2::splat(FOX_SIZE)),
+            ..default()
+        },
+        transform: Transform::from_translation(translation),
+        ..default()
+    });
+
+    commands
+        .entity(fox_camera.single())
+        .insert(LookAtCursorCamera {
+            displayed_at: Some(Rect::from_corners(
+                top_left,
+                top_left + Vec2::splat(FOX_SIZE),
+            )),
+        });
+}
+
+fn fox_looks_at_cursor(mut commands: Commands, fox: Query<Entity, Added<Fox>>) {
+    for fox in &fox {
+        commands.entity(fox).insert(LookAt {
+            body_turn_speed: Some(2.0),
+            ..default()
+        });
+    }
+}
+
 #[derive(Debug, Resource, Component, Clone, Copy)]
 struct SpawnedAt {
     /// Position spawned at
@@ -105,7 +165,7 @@ fn spawn_on_move(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<MouseMaterial>>,
-    mut cursor_moved_events: EventReader<CursorMoved>,
+    mut cursor_moved_events: EventReader<GlobalCursorMoved>,
 ) {
     // These might not be necessary
     const DEBOUNCE: f32 = 0.0; // seconds
@@ -115,9 +175,12 @@ fn spawn_on_move(
         return;
     }
 
-    if let Some(e) = cursor_moved_events.read().last() {
+    if let Some(position) = cursor_moved_events
+        .read()
+        .filter_map(|e| e.cursor.position)
+        .last()
+    {
         let elapsed = time.elapsed_seconds();
-        let position = e.position;
 
         let new_spawn = match last_spawn {
             None => {
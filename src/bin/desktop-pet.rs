@@ -0,0 +1,353 @@
+use std::{
+    f32::consts::{FRAC_PI_2, TAU},
+    time::Duration,
+};
+
+use bevy::{prelude::*, render::camera::ScalingMode, window::close_on_esc};
+use rand::{seq::SliceRandom, Rng};
+
+use streamville::prelude::*;
+
+/// A fox wandering along the bottom of the screen.
+///
+/// It idles, walks and runs on its own, and sometimes gets curious about (or scared of) the cursor.
+/// Click it to make it jump or spin. Clicks anywhere else go through to the desktop.
+fn main() {
+    App::new()
+        .add_plugins(DefaultPlugins.with_primary_window(WindowPreset::transparent_fullscreen()))
+        .insert_resource(ClearColor(Color::NONE))
+        .insert_resource(AmbientLight {
+            color: Color::WHITE,
+            brightness: 500.,
+        })
+        .add_plugins((FoxLookAtPlugin, GlobalCursorPlugin, OfflineRenderPlugin))
+        .add_systems(Startup, setup)
+        .add_systems(
+            Update,
+            (
+                close_on_esc,
+                find_animation_player,
+                (
+                    behave,
+                    react_to_cursor,
+                    react_to_clicks,
+                    play_state_animation,
+                    move_pet,
+                    keep_pet_clickable,
+                )
+                    .chain(),
+            ),
+        )
+        .run();
+}
+
+/// The fox model is roughly this many world units tall, and we render one unit per pixel
+const FOX_HEIGHT: f32 = 80.0;
+const FOX_SCALE: f32 = 1.0;
+
+/// Pixels per second
+const WALK_SPEED: f32 = 60.0;
+const RUN_SPEED: f32 = 250.0;
+
+/// How close (in pixels) the cursor must be before the fox cares
+const CURSOR_REACT_DISTANCE: f32 = 300.0;
+
+/// How long a jump or spin lasts
+const TRICK_SECONDS: f32 = 0.8;
+const JUMP_HEIGHT: f32 = 120.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PetState {
+    Idle,
+    Walk,
+    Run,
+    Jump,
+    Spin,
+}
+
+/// How the fox feels about the cursor right now
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mood {
+    Indifferent,
+    Curious,
+    Shy,
+}
+
+#[derive(Debug, Component)]
+struct Pet {
+    state: PetState,
+    mood: Mood,
+
+    /// When this runs out a new state is chosen
+    behavior_timer: Timer,
+
+    /// +1.0 is walking right, -1.0 is walking left
+    direction: f32,
+
+    /// The animation player somewhere in the fox scene
+    animation_player: Option<Entity>,
+}
+
+#[derive(Resource)]
+struct Animations {
+    survey: Handle<AnimationClip>,
+    walk: Handle<AnimationClip>,
+    run: Handle<AnimationClip>,
+}
+
+impl Animations {
+    fn for_state(&self, state: PetState) -> &Handle<AnimationClip> {
+        match state {
+            PetState::Idle | PetState::Spin => &self.survey,
+            PetState::Walk => &self.walk,
+            PetState::Run | PetState::Jump => &self.run,
+        }
+    }
+}
+
+fn setup(mut commands: Commands, asset_server: Res<AssetServer>, window: Query<&Window>) {
+    commands.insert_resource(Animations {
+        survey: asset_server.load("models/animated/Fox.glb#Animation0"),
+        walk: asset_server.load("models/animated/Fox.glb#Animation1"),
+        run: asset_server.load("models/animated/Fox.glb#Animation2"),
+    });
+
+    // One world unit per pixel, origin in the middle of the screen
+    commands.spawn((
+        Camera3dBundle {
+            projection: OrthographicProjection {
+                scaling_mode: ScalingMode::WindowSize(1.0),
+                ..default()
+            }
+            .into(),
+            transform: Transform::from_xyz(0.0, 0.0, 500.0).looking_at(Vec3::ZERO, Vec3::Y),
+            ..default()
+        },
+        LookAtCursorCamera::default(),
+    ));
+
+    commands.spawn(DirectionalLightBundle {
+        transform: Transform::from_xyz(1.0, 2.0, 3.0).looking_at(Vec3::ZERO, Vec3::Y),
+        ..default()
+    });
+
+    let ground = -window.single().height() / 2.;
+
+    commands.spawn((
+        SceneBundle {
+            scene: asset_server.load("models/animated/Fox.glb#Scene0"),
+            transform: Transform::from_xyz(0.0, ground, 0.0)
+                .with_scale(Vec3::splat(FOX_SCALE))
+                .with_rotation(Quat::from_rotation_y(FRAC_PI_2)),
+            ..default()
+        },
+        Pet {
+            state: PetState::Idle,
+            mood: Mood::Indifferent,
+            behavior_timer: Timer::from_seconds(2.0, TimerMode::Once),
+            direction: 1.0,
+            animation_player: None,
+        },
+        LookAt::default(),
+    ));
+}
+
+fn find_animation_player(
+    mut pets: Query<(Entity, &mut Pet)>,
+    players: Query<Entity, Added<AnimationPlayer>>,
+    parents: Query<&Parent>,
+) {
+    for player in &players {
+        for (pet_entity, mut pet) in &mut pets {
+            if parents.iter_ancestors(player).any(|e| e == pet_entity) {
+                pet.animation_player = Some(player);
+            }
+        }
+    }
+}
+
+/// The state machine: Pick something new to do whenever the behavior timer runs out
+fn behave(time: Res<Time>, mut pets: Query<&mut Pet>) {
+    let mut rng = rand::thread_rng();
+
+    for mut pet in &mut pets {
+        if !pet.behavior_timer.tick(time.delta()).finished() {
+            continue;
+        }
+
+        let (state, seconds) = match pet.state {
+            // Tricks always settle down
+            PetState::Jump | PetState::Spin => (PetState::Idle, rng.gen_range(1.0..3.0)),
+            _ => *[
+                (PetState::Idle, rng.gen_range(2.0..6.0)),
+                (PetState::Walk, rng.gen_range(3.0..8.0)),
+                (PetState::Walk, rng.gen_range(3.0..8.0)),
+                (PetState::Run, rng.gen_range(1.0..3.0)),
+            ]
+            .choose(&mut rng)
+            .expect("non-empty"),
+        };
+
+        if rng.gen_bool(0.3) {
+            pet.direction = -pet.direction;
+        }
+
+        pet.mood = *[Mood::Indifferent, Mood::Curious, Mood::Shy]
+            .choose(&mut rng)
+            .expect("non-empty");
+
+        pet.state = state;
+        pet.behavior_timer = Timer::from_seconds(seconds, TimerMode::Once);
+    }
+}
+
+/// Follows the cursor all over the desktop, not just where the window takes clicks
+fn react_to_cursor(cursor: Query<&GlobalCursor>, mut pets: Query<(&mut Pet, &Transform)>) {
+    let Some(cursor_x) = cursor
+        .get_single()
+        .ok()
+        .and_then(|cursor| cursor.world_position)
+        .map(|cursor| cursor.x)
+    else {
+        return;
+    };
+
+    for (mut pet, transform) in &mut pets {
+        if matches!(pet.state, PetState::Jump | PetState::Spin) {
+            continue;
+        }
+
+        let to_cursor = cursor_x - transform.translation.x;
+        if to_cursor.abs() > CURSOR_REACT_DISTANCE {
+            continue;
+        }
+
+        let direction = match pet.mood {
+            Mood::Indifferent => continue,
+            // Close enough, no need to run into it
+            Mood::Curious if to_cursor.abs() < FOX_HEIGHT => continue,
+            Mood::Curious => to_cursor.signum(),
+            Mood::Shy => -to_cursor.signum(),
+        };
+
+        pet.direction = direction;
+        if pet.state != PetState::Run {
+            pet.state = PetState::Run;
+            pet.behavior_timer = Timer::from_seconds(1.5, TimerMode::Once);
+        }
+    }
+}
+
+fn react_to_clicks(
+    window: Query<&Window>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    mut pets: Query<(&mut Pet, &Transform)>,
+) {
+    if !mouse.just_pressed(MouseButton::Left) {
+        return;
+    }
+
+    let window = window.single();
+    let Some(cursor) = window.cursor_position() else {
+        return;
+    };
+    let cursor = Vec2::new(
+        cursor.x - window.width() / 2.,
+        -cursor.y + window.height() / 2.,
+    );
+
+    for (mut pet, transform) in &mut pets {
+        let fox_center = transform.translation.truncate() + Vec2::Y * FOX_HEIGHT / 2.;
+        if cursor.distance(fox_center) > FOX_HEIGHT {
+            continue;
+        }
+
+        pet.state = if rand::thread_rng().gen_bool(0.5) {
+            PetState::Jump
+        } else {
+            PetState::Spin
+        };
+        pet.behavior_timer = Timer::from_seconds(TRICK_SECONDS, TimerMode::Once);
+    }
+}
+
+fn play_state_animation(
+    animations: Res<Animations>,
+    pets: Query<&Pet>,
+    mut players: Query<&mut AnimationPlayer>,
+) {
+    for pet in &pets {
+        let Some(Ok(mut player)) = pet.animation_player.map(|p| players.get_mut(p)) else {
+            continue;
+        };
+
+        let clip = animations.for_state(pet.state);
+        if !player.is_playing_clip(clip) {
+            player
+                .play_with_transition(clip.clone_weak(), Duration::from_millis(250))
+                .repeat();
+        }
+    }
+}
+
+fn move_pet(time: Res<Time>, window: Query<&Window>, mut pets: Query<(&mut Pet, &mut Transform)>) {
+    let window = window.single();
+    let half_width = window.width() / 2. - FOX_HEIGHT;
+    let ground = -window.height() / 2.;
+
+    for (mut pet, mut transform) in &mut pets {
+        let speed = match pet.state {
+            PetState::Walk => WALK_SPEED,
+            PetState::Run | PetState::Jump => RUN_SPEED,
+            PetState::Idle | PetState::Spin => 0.0,
+        };
+
+        transform.translation.x += pet.direction * speed * time.delta_seconds();
+
+        // Turn around at the edges of the screen
+        if transform.translation.x.abs() > half_width {
+            transform.translation.x = transform.translation.x.clamp(-half_width, half_width);
+            pet.direction = -transform.translation.x.signum();
+        }
+
+        // How far into the current trick we are, 0 to 1
+        let trick = pet.behavior_timer.fraction();
+
+        transform.translation.y = match pet.state {
+            PetState::Jump => ground + JUMP_HEIGHT * 4.0 * trick * (1.0 - trick),
+            _ => ground,
+        };
+
+        // The fox model faces +Z, turn it to face the way it's going
+        let facing = Quat::from_rotation_y(FRAC_PI_2 * pet.direction);
+        transform.rotation = match pet.state {
+            PetState::Spin => facing * Quat::from_rotation_y(TAU * trick),
+            _ => facing,
+        };
+    }
+}
+
+/// Clicks only land on the window around the fox, the rest of the desktop stays usable
+fn keep_pet_clickable(
+    mut windows: Query<(&Window, &mut ClickThrough)>,
+    pets: Query<&Transform, With<Pet>>,
+) {
+    let Ok((window, mut click_through)) = windows.get_single_mut() else {
+        return;
+    };
+
+    let interactive = pets
+        .iter()
+        .map(|transform| {
+            // World to window coordinates, which have the origin top left and Y down
+            let fox_center = transform.translation.truncate() + Vec2::Y * FOX_HEIGHT / 2.;
+            let center = Vec2::new(
+                fox_center.x + window.width() / 2.,
+                window.height() / 2. - fox_center.y,
+            );
+            Rect::from_center_size(center.round(), Vec2::splat(FOX_HEIGHT * 2.))
+        })
+        .collect();
+
+    click_through.set_if_neq(ClickThrough { interactive });
+}
@@ -0,0 +1,71 @@
+use bevy::{prelude::*, window::close_on_esc};
+use bevy_inspector_egui::quick::ResourceInspectorPlugin;
+
+use streamville::prelude::*;
+
+fn main() {
+    App::new()
+        .insert_resource(AmbientLight {
+            color: Color::WHITE,
+            brightness: 500.,
+        })
+        .add_plugins(DefaultPlugins)
+        .add_plugins((
+            WorldAxesGizmoPlugin,
+            OfflineRenderPlugin,
+            FoidsPlugin { count: 300 },
+            ResourceInspectorPlugin::<FoidsParams>::default(),
+        ))
+        .add_systems(Startup, setup)
+        .add_systems(Update, (close_on_esc, show_bounds))
+        .run();
+}
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    // ground
+    commands.spawn(PbrBundle {
+        mesh: meshes.add(Plane3d::default().mesh().size(60.0, 60.0)),
+        material: materials.add(Color::rgb(0.3, 0.5, 0.3)),
+        ..default()
+    });
+
+    // obstacles
+    for (position, radius) in [
+        (Vec3::new(8.0, 0.0, 0.0), 2.0),
+        (Vec3::new(-6.0, 0.0, 6.0), 3.0),
+        (Vec3::new(0.0, 0.0, -10.0), 1.5),
+    ] {
+        commands.spawn((
+            PbrBundle {
+                mesh: meshes.add(Sphere::new(radius)),
+                material: materials.add(Color::GRAY),
+                transform: Transform::from_translation(position),
+                ..default()
+            },
+            FoidObstacle { radius },
+        ));
+    }
+
+    // light
+    commands.spawn(DirectionalLightBundle {
+        transform: Transform::from_xyz(4.0, 8.0, 4.0).looking_at(Vec3::ZERO, Vec3::Y),
+        ..default()
+    });
+
+    // camera
+    commands.spawn(Camera3dBundle {
+        transform: Transform::from_xyz(0.0, 35.0, 35.0).looking_at(Vec3::ZERO, Vec3::Y),
+        ..default()
+    });
+}
+
+fn show_bounds(mut gizmos: Gizmos, params: Res<FoidsParams>) {
+    gizmos.cuboid(
+        Transform::from_scale(params.bounds_half_extents * 2.0 + Vec3::Y * 0.1),
+        Color::YELLOW,
+    );
+}
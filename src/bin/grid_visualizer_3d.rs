@@ -1,10 +1,16 @@
 use bevy::prelude::*;
-use streamville::blender_cam;
+use streamville::{
+    blender_cam, offline_render::OfflineRenderPlugin, window_layout::WindowLayoutPlugin,
+};
 
 fn main() {
     App::new()
         .add_plugins(DefaultPlugins)
-        .add_plugins(blender_cam::BlenderCamPlugin)
+        .add_plugins((
+            blender_cam::BlenderCamPlugin,
+            OfflineRenderPlugin,
+            WindowLayoutPlugin::default(),
+        ))
         .add_systems(Startup, setup)
         .run();
 }
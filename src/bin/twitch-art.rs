@@ -0,0 +1,423 @@
+use std::{
+    fs::File,
+    io::{BufReader, Cursor},
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+use bevy::{
+    app::AppExit,
+    prelude::*,
+    render::{
+        camera::RenderTarget,
+        render_resource::{AsBindGroup, ShaderRef},
+        view::RenderLayers,
+    },
+    sprite::{Material2d, Material2dPlugin, MaterialMesh2dBundle},
+};
+use image::{
+    codecs::{
+        gif::{GifEncoder, Repeat},
+        jpeg::JpegEncoder,
+    },
+    imageops::FilterType,
+    Delay, DynamicImage, Frame, ImageFormat,
+};
+
+use streamville::{png_capture::PngCaptureFailed, prelude::*, render_util::RenderTargetBuilder};
+
+/// Generates a Twitch profile picture and banner from a shader.
+///
+/// Writes `profile.{png,jpg,gif}` and `banner.{png,jpg}`, all within Twitch's size limit.
+///
+/// # Usage
+///
+/// ```text
+/// twitch-art [--shader shaders/twitch_art.wgsl] [--seed 1] [--p0 4] [--p1 2] [--p2 0] [--p3 0]
+///            [--frames 60] [--out twitch-art]
+/// ```
+///
+/// The shader gets a `seed`, a looping `phase` (animated for the GIF), the `aspect` ratio and
+/// the four `params`. See `assets/shaders/twitch_art.wgsl`.
+fn main() {
+    let args = Args::parse();
+    SHADER
+        .set(args.shader.clone())
+        .expect("only set once, before the app starts");
+
+    App::new()
+        .add_plugins(DefaultPlugins.set(WindowPlugin {
+            primary_window: Some(Window {
+                visible: false,
+                ..default()
+            }),
+            ..default()
+        }))
+        .add_plugins((
+            Material2dPlugin::<TwitchArtMaterial>::default(),
+            PngCapturePlugin,
+            RenderTargetPoolPlugin,
+        ))
+        .insert_resource(args)
+        .init_resource::<Progress>()
+        .add_systems(Startup, setup)
+        .add_systems(Update, (render_art, encode_when_done).chain())
+        .run();
+}
+
+/// "Must be JPEG, PNG, or GIF and cannot exceed 10MB"
+const MAX_FILE_BYTES: usize = 10 * 1000 * 1000;
+
+const PROFILE_SIZE: UVec2 = UVec2::splat(800);
+/// Recommended banner size
+const BANNER_SIZE: UVec2 = UVec2::new(1200, 480);
+
+/// Frames to let the shader compile before capturing
+const WARMUP_FRAMES: u32 = 30;
+
+/// Total length of the GIF loop
+const GIF_LOOP_MS: u32 = 3000;
+
+static SHADER: OnceLock<String> = OnceLock::new();
+
+#[derive(Debug, Clone, Resource)]
+struct Args {
+    shader: String,
+    seed: f32,
+    params: Vec4,
+    gif_frames: u32,
+    out: PathBuf,
+}
+
+impl Args {
+    fn parse() -> Self {
+        let mut parsed = Self {
+            shader: "shaders/twitch_art.wgsl".to_owned(),
+            seed: 1.0,
+            params: Vec4::new(4.0, 2.0, 0.0, 0.0),
+            gif_frames: 60,
+            out: PathBuf::from("twitch-art"),
+        };
+
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            let value = args.next().unwrap_or_else(|| panic!("{arg} needs a value"));
+            let number = || -> f32 {
+                value
+                    .parse()
+                    .unwrap_or_else(|_| panic!("{arg} needs a number, got {value}"))
+            };
+
+            match arg.as_str() {
+                "--shader" => parsed.shader = value.clone(),
+                "--seed" => parsed.seed = number(),
+                "--p0" => parsed.params.x = number(),
+                "--p1" => parsed.params.y = number(),
+                "--p2" => parsed.params.z = number(),
+                "--p3" => parsed.params.w = number(),
+                "--frames" => {
+                    parsed.gif_frames = match value.parse() {
+                        Ok(frames) if frames >= 1 => frames,
+                        _ => panic!("{arg} needs a whole number of at least 1, got {value}"),
+                    }
+                }
+                "--out" => parsed.out = PathBuf::from(&value),
+                _ => panic!("Unknown argument {arg}"),
+            }
+        }
+
+        parsed
+    }
+}
+
+#[derive(Asset, TypePath, AsBindGroup, Debug, Clone)]
+struct TwitchArtMaterial {
+    #[uniform(0)]
+    seed: f32,
+    #[uniform(0)]
+    phase: f32,
+    #[uniform(0)]
+    aspect: f32,
+    #[uniform(0)]
+    params: Vec4,
+}
+
+impl Material2d for TwitchArtMaterial {
+    fn fragment_shader() -> ShaderRef {
+        let shader = SHADER.get().cloned().unwrap_or_default();
+        ShaderRef::Path(shader.into())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ArtKind {
+    Profile,
+    Banner,
+}
+
+#[derive(Debug, Component)]
+struct ArtTarget {
+    kind: ArtKind,
+    image: Handle<Image>,
+    material: Handle<TwitchArtMaterial>,
+}
+
+#[derive(Debug, Default, Resource)]
+struct Progress {
+    frame: u32,
+    finished: u32,
+}
+
+fn setup(
+    mut commands: Commands,
+    args: Res<Args>,
+    mut images: ResMut<Assets<Image>>,
+    mut pool: ResMut<RenderTargetPool>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<TwitchArtMaterial>>,
+) {
+    for (layer, kind, size) in [
+        (1, ArtKind::Profile, PROFILE_SIZE),
+        (2, ArtKind::Banner, BANNER_SIZE),
+    ] {
+        let image = pool.lend(
+            RenderTargetBuilder::new(size)
+                .label("twitch_art_target")
+                .readback(),
+            &mut images,
+        );
+
+        let material = materials.add(TwitchArtMaterial {
+            seed: args.seed,
+            phase: 0.0,
+            aspect: size.x as f32 / size.y as f32,
+            params: args.params,
+        });
+
+        // Each target has its own camera and quad, kept apart by render layers
+        commands.spawn((
+            Camera2dBundle {
+                camera: Camera {
+                    target: RenderTarget::Image(image.clone()),
+                    ..default()
+                },
+                ..default()
+            },
+            RenderLayers::layer(layer),
+            PooledRenderTarget(image.clone()),
+        ));
+
+        commands.spawn((
+            MaterialMesh2dBundle {
+                mesh: meshes.add(Rectangle::default()).into(),
+                material: material.clone(),
+                transform: Transform::from_scale(size.as_vec2().extend(1.0)),
+                ..default()
+            },
+            RenderLayers::layer(layer),
+            ArtTarget {
+                kind,
+                image,
+                material,
+            },
+        ));
+    }
+}
+
+fn gif_frame_path(out: &Path, frame: u32) -> PathBuf {
+    out.join("frames").join(format!("profile-{frame:04}.png"))
+}
+
+/// After warming up: Capture the stills, then step the phase once per frame for the GIF
+fn render_art(
+    args: Res<Args>,
+    mut progress: ResMut<Progress>,
+    targets: Query<&ArtTarget>,
+    mut materials: ResMut<Assets<TwitchArtMaterial>>,
+    mut captures: EventWriter<CapturePng>,
+) {
+    let frame = progress.frame;
+    progress.frame += 1;
+
+    let Some(gif_frame) = frame.checked_sub(WARMUP_FRAMES) else {
+        return;
+    };
+
+    if gif_frame >= args.gif_frames {
+        return;
+    }
+
+    for target in &targets {
+        let path = match (target.kind, gif_frame) {
+            (ArtKind::Profile, _) => gif_frame_path(&args.out, gif_frame),
+            (ArtKind::Banner, 0) => args.out.join("banner.png"),
+            (ArtKind::Banner, _) => continue,
+        };
+
+        if let Some(material) = materials.get_mut(&target.material) {
+            material.phase = gif_frame as f32 / args.gif_frames as f32;
+        }
+
+        captures.send(CapturePng {
+            path: Some(path),
+            ..CapturePng::new(CaptureTarget::Image(target.image.clone()), "twitch_art")
+        });
+    }
+}
+
+fn encode_when_done(
+    args: Res<Args>,
+    mut progress: ResMut<Progress>,
+    mut saved: EventReader<PngSaved>,
+    mut failed: EventReader<PngCaptureFailed>,
+    mut exit: EventWriter<AppExit>,
+) {
+    let failures = failed.read().count();
+    if failures > 0 {
+        error!("{failures} captures failed, not encoding");
+        exit.send(AppExit);
+        return;
+    }
+
+    progress.finished += saved.read().count() as u32;
+
+    // One banner plus all the profile frames
+    if progress.finished < args.gif_frames + 1 {
+        return;
+    }
+
+    if let Err(e) = encode_all(&args) {
+        error!("Could not encode: {e}");
+    }
+
+    exit.send(AppExit);
+}
+
+type EncodeResult<T> = Result<T, Box<dyn std::error::Error>>;
+
+fn encode_all(args: &Args) -> EncodeResult<()> {
+    let load = |path: &Path| -> EncodeResult<DynamicImage> {
+        Ok(image::load(
+            BufReader::new(File::open(path)?),
+            ImageFormat::Png,
+        )?)
+    };
+
+    let profile = load(&gif_frame_path(&args.out, 0))?;
+    let banner = load(&args.out.join("banner.png"))?;
+
+    for (name, image) in [("profile", &profile), ("banner", &banner)] {
+        write_limited(&args.out.join(format!("{name}.png")), image, encode_png)?;
+        write_limited(&args.out.join(format!("{name}.jpg")), image, encode_jpeg)?;
+    }
+
+    let frames = (0..args.gif_frames)
+        .map(|frame| load(&gif_frame_path(&args.out, frame)))
+        .collect::<EncodeResult<Vec<_>>>()?;
+    write_gif(&args.out.join("profile.gif"), &frames)?;
+
+    info!("Twitch art written to {}", args.out.display());
+    Ok(())
+}
+
+/// Encodes at decreasing quality (1.0 is best) until under the limit, then writes it
+fn write_limited(
+    path: &Path,
+    image: &DynamicImage,
+    encode: impl Fn(&DynamicImage, f32) -> EncodeResult<Vec<u8>>,
+) -> EncodeResult<()> {
+    let mut quality = 1.0;
+
+    loop {
+        let bytes = encode(image, quality)?;
+
+        if bytes.len() <= MAX_FILE_BYTES {
+            std::fs::write(path, &bytes)?;
+            info!(
+                "{}: {} bytes at quality {quality:.2}",
+                path.display(),
+                bytes.len()
+            );
+            return Ok(());
+        }
+
+        if quality < 0.1 {
+            return Err(format!("{} is too big even at lowest quality", path.display()).into());
+        }
+
+        warn!(
+            "{} is {} bytes at quality {quality:.2}, too big",
+            path.display(),
+            bytes.len()
+        );
+        quality *= 0.75;
+    }
+}
+
+/// PNG is lossless, so lower quality means fewer pixels
+fn encode_png(image: &DynamicImage, quality: f32) -> EncodeResult<Vec<u8>> {
+    let image = if quality < 1.0 {
+        let scale = |n: u32| (n as f32 * quality) as u32;
+        image.resize(
+            scale(image.width()),
+            scale(image.height()),
+            FilterType::Lanczos3,
+        )
+    } else {
+        image.clone()
+    };
+
+    let mut bytes = vec![];
+    image.write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)?;
+    Ok(bytes)
+}
+
+fn encode_jpeg(image: &DynamicImage, quality: f32) -> EncodeResult<Vec<u8>> {
+    let mut bytes = vec![];
+    JpegEncoder::new_with_quality(&mut bytes, (quality * 95.0) as u8)
+        .encode_image(&image.to_rgb8())?;
+    Ok(bytes)
+}
+
+/// Drops every other frame (keeping the loop length) until the GIF is under the limit
+fn write_gif(path: &Path, frames: &[DynamicImage]) -> EncodeResult<()> {
+    let mut step = 1;
+
+    loop {
+        let kept: Vec<_> = frames.iter().step_by(step).collect();
+        let delay = Delay::from_numer_denom_ms(GIF_LOOP_MS, kept.len() as u32);
+
+        let mut bytes = vec![];
+        {
+            let mut encoder = GifEncoder::new_with_speed(&mut bytes, 10);
+            encoder.set_repeat(Repeat::Infinite)?;
+            encoder.encode_frames(
+                kept.iter()
+                    .map(|frame| Frame::from_parts(frame.to_rgba8(), 0, 0, delay)),
+            )?;
+        }
+
+        if bytes.len() <= MAX_FILE_BYTES {
+            std::fs::write(path, &bytes)?;
+            info!(
+                "{}: {} bytes with {} frames",
+                path.display(),
+                bytes.len(),
+                kept.len()
+            );
+            return Ok(());
+        }
+
+        if kept.len() <= 2 {
+            return Err(format!("{} is too big even with 2 frames", path.display()).into());
+        }
+
+        warn!(
+            "{} is {} bytes with {} frames, too big",
+            path.display(),
+            bytes.len(),
+            kept.len()
+        );
+        step *= 2;
+    }
+}
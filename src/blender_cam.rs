@@ -3,8 +3,11 @@ use bevy::{
     prelude::*,
 };
 
+use crate::window_layout::PersistWindowLayout;
+
 /// Spawns and controls a camera similar to Blender.
-/// Has late ordering and a separate window.
+/// Has late ordering and a separate window, which remembers its layout with
+/// [`crate::window_layout::WindowLayoutPlugin`].
 ///
 /// # Controls
 ///
@@ -89,10 +92,13 @@ fn wheel_hold_pan(
 
 fn spawn_blender_cam(mut commands: Commands) {
     let win_id = commands
-        .spawn(Window {
-            title: "Blender Cam".to_owned(),
-            ..default()
-        })
+        .spawn((
+            Window {
+                title: "Blender Cam".to_owned(),
+                ..default()
+            },
+            PersistWindowLayout::new("blender_cam"),
+        ))
         .id();
 
     let _cam_id = commands
@@ -0,0 +1,502 @@
+use std::f32::consts::TAU;
+
+use bevy::{math::cubic_splines::CubicCurve, prelude::*};
+use serde::Deserialize;
+
+use crate::{
+    bullet_game::{BulletGameEvent, GameSet},
+    bullet_pattern::{BulletEmitter, BulletPattern, BulletPatternSet},
+    bullets::{Bullet, BulletArena, Bullets, DespawnReason},
+    simulation::SimulationAssets,
+};
+
+/// Moves entities along [`FollowPath`]s and scripts [`Boss`] encounters, in [`BossSet`].
+///
+/// A boss goes through its [`BossPhase`]s as time passes or its health drops, each with its
+/// own emitters, spawned as its children. Bullets are cleared between phases.
+/// Restarting the game restarts bosses too.
+pub struct BossPlugin;
+
+impl Plugin for BossPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<BulletGameEvent>()
+            .add_event::<BossEvent>()
+            .init_resource::<SimulationAssets>()
+            .configure_sets(
+                FixedUpdate,
+                BossSet.after(GameSet::Player).before(BulletPatternSet),
+            )
+            .add_systems(PreUpdate, preload_boss_patterns)
+            .add_systems(
+                FixedUpdate,
+                (restart_bosses, advance_phases, follow_paths)
+                    .chain()
+                    .in_set(BossSet),
+            );
+    }
+}
+
+/// Bosses change phases and things move along their paths here, before emitters fire
+#[derive(Debug, Clone, PartialEq, Eq, Hash, SystemSet)]
+pub struct BossSet;
+
+/// A path on the arena plane, see [`crate::bullets::ArenaPlane`]
+#[derive(Debug, Clone, Deserialize)]
+#[serde(try_from = "ArenaPathFile")]
+pub enum ArenaPath {
+    /// From `from` to `to` in `duration` seconds, then back, and so on
+    Line { from: Vec2, to: Vec2, duration: f32 },
+
+    /// Counterclockwise once per `period` seconds, starting `angle` degrees from the X axis
+    Circle {
+        center: Vec2,
+        radius: f32,
+        period: f32,
+        angle: f32,
+    },
+
+    /// Smoothly through points and back to the first, see [`ArenaPath::spline`]
+    Spline {
+        curve: CubicCurve<Vec2>,
+        points: usize,
+        segment: f32,
+    },
+}
+
+impl ArenaPath {
+    /// Through `points` in order, looping, `segment` seconds from one to the next. Needs a point.
+    pub fn spline(points: &[Vec2], segment: f32) -> Self {
+        assert!(!points.is_empty(), "a spline needs points");
+
+        // Wrapped around, so it's closed and smooth where it starts
+        let n = points.len();
+        let control: Vec<_> = (0..n + 3).map(|i| points[(i + n - 1) % n]).collect();
+
+        Self::Spline {
+            curve: CubicCardinalSpline::new_catmull_rom(control).to_curve(),
+            points: n,
+            segment,
+        }
+    }
+
+    /// Where it is `time` seconds in
+    pub fn at(&self, time: f32) -> Vec2 {
+        match self {
+            ArenaPath::Line { from, to, duration } => {
+                let t = if *duration > 0.0 {
+                    let t = (time / duration).rem_euclid(2.0);
+                    if t > 1.0 {
+                        2.0 - t
+                    } else {
+                        t
+                    }
+                } else {
+                    0.0
+                };
+                from.lerp(*to, t)
+            }
+            ArenaPath::Circle {
+                center,
+                radius,
+                period,
+                angle,
+            } => {
+                let turns = if *period > 0.0 { time / period } else { 0.0 };
+                *center + Vec2::from_angle(angle.to_radians() + turns * TAU) * *radius
+            }
+            ArenaPath::Spline {
+                curve,
+                points,
+                segment,
+            } => {
+                let t = if *segment > 0.0 {
+                    (time / segment).rem_euclid(*points as f32)
+                } else {
+                    0.0
+                };
+                curve.position(t)
+            }
+        }
+    }
+}
+
+/// How [`ArenaPath`]s are written, splines as their points
+#[derive(Deserialize)]
+enum ArenaPathFile {
+    Line {
+        from: Vec2,
+        to: Vec2,
+        duration: f32,
+    },
+    Circle {
+        center: Vec2,
+        radius: f32,
+        period: f32,
+        #[serde(default)]
+        angle: f32,
+    },
+    Spline {
+        points: Vec<Vec2>,
+        segment: f32,
+    },
+}
+
+impl TryFrom<ArenaPathFile> for ArenaPath {
+    type Error = String;
+
+    fn try_from(path: ArenaPathFile) -> Result<Self, String> {
+        Ok(match path {
+            ArenaPathFile::Line { from, to, duration } => Self::Line { from, to, duration },
+            ArenaPathFile::Circle {
+                center,
+                radius,
+                period,
+                angle,
+            } => Self::Circle {
+                center,
+                radius,
+                period,
+                angle,
+            },
+            ArenaPathFile::Spline { points, .. } if points.is_empty() => {
+                return Err("a spline needs points".to_owned())
+            }
+            ArenaPathFile::Spline { points, segment } => Self::spline(&points, segment),
+        })
+    }
+}
+
+/// Moves along `path`, relative to its parent if it has one, keeping its height
+#[derive(Debug, Clone, Component)]
+pub struct FollowPath {
+    pub path: ArenaPath,
+
+    /// Seconds since it started
+    pub elapsed: f32,
+}
+
+impl FollowPath {
+    pub fn new(path: ArenaPath) -> Self {
+        Self { path, elapsed: 0.0 }
+    }
+}
+
+/// Spawn one with a [`Transform`], and e.g. a model and a [`FollowPath`]
+#[derive(Debug, Clone, Component)]
+pub struct Boss {
+    pub max_health: f32,
+    pub health: f32,
+
+    pub phases: Vec<BossPhase>,
+
+    /// Index into [`Self::phases`], their length once defeated
+    pub phase: usize,
+    /// Seconds since the current phase started
+    pub phase_time: f32,
+
+    /// The phase whose emitters are spawned
+    spawned: Option<usize>,
+}
+
+impl Boss {
+    pub fn new(health: f32, phases: Vec<BossPhase>) -> Self {
+        Self {
+            max_health: health,
+            health,
+            phases,
+            phase: 0,
+            phase_time: 0.0,
+            spawned: None,
+        }
+    }
+
+    /// Damage it from the simulation, so the phase changes on the next tick
+    pub fn damage(&mut self, amount: f32) {
+        self.health = (self.health - amount).max(0.0);
+    }
+
+    pub fn defeated(&self) -> bool {
+        self.phase >= self.phases.len()
+    }
+
+    fn phase_over(&self) -> bool {
+        let Some(phase) = self.phases.get(self.phase) else {
+            return false;
+        };
+
+        match phase.ends {
+            PhaseEnd::After(seconds) => self.phase_time >= seconds,
+            PhaseEnd::HealthBelow(fraction) => self.health < self.max_health * fraction,
+            PhaseEnd::AfterOrHealthBelow(seconds, fraction) => {
+                self.phase_time >= seconds || self.health < self.max_health * fraction
+            }
+            PhaseEnd::Never => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BossPhase {
+    pub ends: PhaseEnd,
+    pub emitters: Vec<PhaseEmitter>,
+
+    /// Where the boss goes during this phase, if not where it was going
+    pub path: Option<ArenaPath>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PhaseEnd {
+    /// Seconds into the phase
+    After(f32),
+    /// A fraction of [`Boss::max_health`]
+    HealthBelow(f32),
+    /// Whichever comes first
+    AfterOrHealthBelow(f32, f32),
+    Never,
+}
+
+/// An emitter for one phase, a child of the boss
+#[derive(Debug, Clone)]
+pub struct PhaseEmitter {
+    pub pattern: Handle<BulletPattern>,
+
+    /// On the arena plane, from the boss
+    pub offset: Vec2,
+
+    /// Replaces `offset`
+    pub path: Option<ArenaPath>,
+}
+
+impl PhaseEmitter {
+    pub fn new(pattern: Handle<BulletPattern>) -> Self {
+        Self {
+            pattern,
+            offset: Vec2::ZERO,
+            path: None,
+        }
+    }
+
+    pub fn offset(mut self, offset: Vec2) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    pub fn path(mut self, path: ArenaPath) -> Self {
+        self.path = Some(path);
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Event)]
+pub enum BossEvent {
+    PhaseStarted { boss: Entity, phase: usize },
+    Defeated { boss: Entity },
+}
+
+/// Marks the emitters of a boss's current phase
+#[derive(Debug, Clone, Copy, Component)]
+struct BossEmitter;
+
+/// Patterns of later phases are loaded before the simulation starts, not when the phase does
+fn preload_boss_patterns(mut assets: ResMut<SimulationAssets>, bosses: Query<&Boss, Added<Boss>>) {
+    for boss in &bosses {
+        for phase in &boss.phases {
+            for emitter in &phase.emitters {
+                assets.push(emitter.pattern.clone().untyped());
+            }
+        }
+    }
+}
+
+fn restart_bosses(
+    mut events: EventReader<BulletGameEvent>,
+    mut bosses: Query<&mut Boss>,
+    mut followers: Query<&mut FollowPath>,
+) {
+    if !events
+        .read()
+        .any(|event| *event == BulletGameEvent::Restarted)
+    {
+        return;
+    }
+
+    for mut boss in &mut bosses {
+        boss.health = boss.max_health;
+        boss.phase = 0;
+        boss.phase_time = 0.0;
+        boss.spawned = None;
+    }
+    for mut follow in &mut followers {
+        follow.elapsed = 0.0;
+    }
+}
+
+fn advance_phases(
+    mut commands: Commands,
+    time: Res<Time>,
+    arena: Res<BulletArena>,
+    mut bosses: Query<(Entity, &mut Boss, Option<&Children>)>,
+    emitters: Query<(), With<BossEmitter>>,
+    existing: Query<(Entity, &Bullet)>,
+    mut bullets: Bullets,
+    mut events: EventWriter<BossEvent>,
+) {
+    for (entity, mut boss, children) in &mut bosses {
+        boss.phase_time += time.delta_seconds();
+
+        while boss.phase_over() {
+            boss.phase += 1;
+            boss.phase_time = 0.0;
+        }
+
+        if boss.spawned == Some(boss.phase) {
+            continue;
+        }
+
+        // Phases after the first start on a clean slate
+        if boss.spawned.is_some() {
+            for (cleared, bullet) in &existing {
+                bullets.despawn(cleared, bullet.position, DespawnReason::Cleared);
+            }
+        }
+        boss.spawned = Some(boss.phase);
+
+        for &child in children.into_iter().flatten() {
+            if emitters.contains(child) {
+                commands.entity(child).despawn_recursive();
+            }
+        }
+
+        let Some(phase) = boss.phases.get(boss.phase) else {
+            info!("Boss defeated");
+            events.send(BossEvent::Defeated { boss: entity });
+            continue;
+        };
+
+        if let Some(path) = &phase.path {
+            commands
+                .entity(entity)
+                .insert(FollowPath::new(path.clone()));
+        }
+
+        commands.entity(entity).with_children(|boss| {
+            for emitter in &phase.emitters {
+                let mut spawned = boss.spawn((
+                    TransformBundle::from_transform(Transform::from_translation(
+                        arena.plane.unproject(emitter.offset, 0.0),
+                    )),
+                    BulletEmitter::new(emitter.pattern.clone()),
+                    BossEmitter,
+                ));
+                if let Some(path) = &emitter.path {
+                    spawned.insert(FollowPath::new(path.clone()));
+                }
+            }
+        });
+
+        info!("Boss phase {}", boss.phase + 1);
+        events.send(BossEvent::PhaseStarted {
+            boss: entity,
+            phase: boss.phase,
+        });
+    }
+}
+
+fn follow_paths(
+    time: Res<Time>,
+    arena: Res<BulletArena>,
+    mut followers: Query<(&mut Transform, &mut FollowPath)>,
+) {
+    for (mut transform, mut follow) in &mut followers {
+        let height = arena.plane.height(transform.translation);
+        transform.translation = arena
+            .plane
+            .unproject(follow.path.at(follow.elapsed), height);
+        follow.elapsed += time.delta_seconds();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(actual: Vec2, expected: Vec2) {
+        assert!(
+            actual.distance(expected) < 1e-4,
+            "{actual} isn't {expected}"
+        );
+    }
+
+    #[test]
+    fn lines_go_back_and_forth() {
+        let line = ArenaPath::Line {
+            from: Vec2::ZERO,
+            to: Vec2::new(2.0, 0.0),
+            duration: 2.0,
+        };
+
+        assert_near(line.at(0.0), Vec2::ZERO);
+        assert_near(line.at(1.0), Vec2::new(1.0, 0.0));
+        assert_near(line.at(2.0), Vec2::new(2.0, 0.0));
+        assert_near(line.at(3.0), Vec2::new(1.0, 0.0));
+        assert_near(line.at(4.0), Vec2::ZERO);
+        assert_near(line.at(5.5), Vec2::new(1.5, 0.0));
+        // Before it started, mirrored
+        assert_near(line.at(-1.0), Vec2::new(1.0, 0.0));
+
+        let still = ArenaPath::Line {
+            from: Vec2::ONE,
+            to: Vec2::ZERO,
+            duration: 0.0,
+        };
+        assert_near(still.at(3.0), Vec2::ONE);
+    }
+
+    #[test]
+    fn circles_go_counterclockwise() {
+        let circle = ArenaPath::Circle {
+            center: Vec2::new(1.0, 1.0),
+            radius: 2.0,
+            period: 4.0,
+            angle: 90.0,
+        };
+
+        assert_near(circle.at(0.0), Vec2::new(1.0, 3.0));
+        assert_near(circle.at(1.0), Vec2::new(-1.0, 1.0));
+        assert_near(circle.at(4.0), circle.at(0.0));
+    }
+
+    #[test]
+    fn splines_pass_through_their_points_and_close() {
+        let points = [
+            Vec2::new(0.0, 0.0),
+            Vec2::new(1.0, 0.0),
+            Vec2::new(1.0, 1.0),
+            Vec2::new(0.0, 1.0),
+        ];
+        let spline = ArenaPath::spline(&points, 0.5);
+
+        for (i, point) in points.iter().enumerate() {
+            assert_near(spline.at(i as f32 * 0.5), *point);
+        }
+
+        // Back at the start after the last point, and smoothly so
+        assert_near(spline.at(2.0), points[0]);
+        assert_near(spline.at(2.25), spline.at(0.25));
+        let before = spline.at(2.0 - 1e-3);
+        let after = spline.at(1e-3);
+        assert!(before.distance(after) < 1e-2, "{before} to {after}");
+    }
+
+    #[test]
+    fn empty_splines_dont_load() {
+        let error = ron::from_str::<ArenaPath>("Spline(points: [], segment: 1.0)").unwrap_err();
+        assert!(
+            error.to_string().contains("a spline needs points"),
+            "{error}"
+        );
+
+        let path = ron::from_str::<ArenaPath>("Spline(points: [(1.0, 2.0)], segment: 1.0)");
+        assert_near(path.unwrap().at(0.5), Vec2::new(1.0, 2.0));
+    }
+}
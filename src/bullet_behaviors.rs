@@ -0,0 +1,238 @@
+use std::f32::consts::TAU;
+
+use bevy::{ecs::system::EntityCommands, prelude::*};
+use serde::Deserialize;
+
+use crate::{
+    bullet_game::Player,
+    bullet_pattern::Speed,
+    bullets::{Bullet, BulletSet, Bullets, DespawnReason},
+};
+
+/// Steers [`Bullet`]s with behavior components, one system each, in [`BulletSet::Steer`].
+///
+/// Any mix of [`Accelerate`], [`Curve`], [`Homing`], [`AimAtPlayer`] and [`Split`] can be on a
+/// bullet, e.g. through [`crate::bullet_pattern::Pattern::Behave`]. They're removed when the
+/// bullet is despawned, like anything else inserted through [`Bullets::spawn`].
+pub struct BulletBehaviorsPlugin;
+
+impl Plugin for BulletBehaviorsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            FixedUpdate,
+            (accelerate, curve, home, aim_at_player, split)
+                .chain()
+                .in_set(BulletSet::Steer),
+        );
+    }
+}
+
+/// Changes speed by `rate` units per second per second, replacing the bullet's [`Speed`]
+#[derive(Debug, Clone, Copy, PartialEq, Component, Deserialize)]
+pub struct Accelerate {
+    /// Negative to slow down
+    pub rate: f32,
+    #[serde(default)]
+    pub min: f32,
+    #[serde(default)]
+    pub max: Option<f32>,
+}
+
+/// Turns `rate` degrees per second, counterclockwise
+#[derive(Debug, Clone, Copy, PartialEq, Component, Deserialize)]
+pub struct Curve {
+    pub rate: f32,
+}
+
+/// Turns towards the closest [`Player`] by at most `turn_rate` degrees per second,
+/// once it's `delay` seconds old
+#[derive(Debug, Clone, Copy, PartialEq, Component, Deserialize)]
+pub struct Homing {
+    pub turn_rate: f32,
+    #[serde(default)]
+    pub delay: f32,
+}
+
+/// Turns straight at the closest [`Player`] once, when it's `delay` seconds old
+#[derive(Debug, Clone, Copy, PartialEq, Component, Deserialize)]
+pub struct AimAtPlayer {
+    pub delay: f32,
+}
+
+/// Splits into `count` bullets once it's `after` seconds old, spread across `spread` degrees
+/// around where it was going. They keep its other behaviors.
+#[derive(Debug, Clone, Copy, PartialEq, Component, Deserialize)]
+pub struct Split {
+    pub after: f32,
+    pub count: u32,
+    pub spread: f32,
+    pub speed: Speed,
+}
+
+/// Behaviors to give a bullet, each optional
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct BulletBehaviors {
+    pub accelerate: Option<Accelerate>,
+    pub curve: Option<Curve>,
+    pub homing: Option<Homing>,
+    pub aim_at_player: Option<AimAtPlayer>,
+    pub split: Option<Split>,
+}
+
+impl BulletBehaviors {
+    /// Behaviors of `self`, and of `other` where `self` has none
+    pub fn or(self, other: Self) -> Self {
+        Self {
+            accelerate: self.accelerate.or(other.accelerate),
+            curve: self.curve.or(other.curve),
+            homing: self.homing.or(other.homing),
+            aim_at_player: self.aim_at_player.or(other.aim_at_player),
+            split: self.split.or(other.split),
+        }
+    }
+
+    pub fn insert(self, entity: &mut EntityCommands) {
+        if let Some(accelerate) = self.accelerate {
+            entity.insert(accelerate);
+        }
+        if let Some(curve) = self.curve {
+            entity.insert(curve);
+        }
+        if let Some(homing) = self.homing {
+            entity.insert(homing);
+        }
+        if let Some(aim_at_player) = self.aim_at_player {
+            entity.insert(aim_at_player);
+        }
+        if let Some(split) = self.split {
+            entity.insert(split);
+        }
+    }
+}
+
+fn closest_player<'a>(players: impl Iterator<Item = &'a Player>, position: Vec2) -> Option<Vec2> {
+    players.map(|player| player.position).min_by(|a, b| {
+        a.distance_squared(position)
+            .total_cmp(&b.distance_squared(position))
+    })
+}
+
+fn accelerate(time: Res<Time>, mut bullets: Query<(&mut Bullet, &Accelerate)>) {
+    let dt = time.delta_seconds();
+
+    for (mut bullet, accelerate) in &mut bullets {
+        let speed = (bullet.speed.at(bullet.age) + accelerate.rate * dt)
+            .max(accelerate.min)
+            .min(accelerate.max.unwrap_or(f32::INFINITY));
+        bullet.speed = Speed::Constant(speed);
+    }
+}
+
+fn curve(time: Res<Time>, mut bullets: Query<(&mut Bullet, &Curve)>) {
+    let dt = time.delta_seconds();
+
+    for (mut bullet, curve) in &mut bullets {
+        let turn = Vec2::from_angle((curve.rate * dt).to_radians());
+        bullet.direction = turn.rotate(bullet.direction).normalize();
+    }
+}
+
+fn home(time: Res<Time>, players: Query<&Player>, mut bullets: Query<(&mut Bullet, &Homing)>) {
+    let dt = time.delta_seconds();
+
+    for (mut bullet, homing) in &mut bullets {
+        if bullet.age < homing.delay {
+            continue;
+        }
+        let Some(target) = closest_player(players.iter(), bullet.position) else {
+            continue;
+        };
+
+        let wanted = (target - bullet.position).normalize_or_zero();
+        if wanted == Vec2::ZERO {
+            continue;
+        }
+
+        let max_turn = (homing.turn_rate * dt).to_radians();
+        let turn = bullet
+            .direction
+            .angle_between(wanted)
+            .clamp(-max_turn, max_turn);
+        bullet.direction = Vec2::from_angle(turn).rotate(bullet.direction).normalize();
+    }
+}
+
+fn aim_at_player(
+    mut commands: Commands,
+    players: Query<&Player>,
+    mut bullets: Query<(Entity, &mut Bullet, &AimAtPlayer)>,
+) {
+    for (entity, mut bullet, aim) in &mut bullets {
+        if bullet.age < aim.delay {
+            continue;
+        }
+        commands.entity(entity).remove::<AimAtPlayer>();
+
+        let Some(target) = closest_player(players.iter(), bullet.position) else {
+            continue;
+        };
+        let wanted = (target - bullet.position).normalize_or_zero();
+        if wanted != Vec2::ZERO {
+            bullet.direction = wanted;
+        }
+    }
+}
+
+/// What split bullets keep
+type Inherited = (
+    Option<&'static Accelerate>,
+    Option<&'static Curve>,
+    Option<&'static Homing>,
+    Option<&'static AimAtPlayer>,
+);
+
+fn split(
+    splitting: Query<(Entity, &Bullet, &Split)>,
+    inherited: Query<Inherited>,
+    mut bullets: Bullets,
+) {
+    for (entity, bullet, split) in &splitting {
+        if bullet.age < split.after {
+            continue;
+        }
+        bullets.despawn(entity, bullet.position, DespawnReason::Split);
+
+        let Ok((accelerate, curve, homing, aim_at_player)) = inherited.get(entity) else {
+            continue;
+        };
+        let behaviors = BulletBehaviors {
+            accelerate: accelerate.copied(),
+            curve: curve.copied(),
+            homing: homing.copied(),
+            aim_at_player: aim_at_player.copied(),
+            split: None,
+        };
+
+        // Around a full circle, the first and last would overlap
+        let spread = split.spread.to_radians();
+        let step = if spread >= TAU {
+            TAU / split.count as f32
+        } else if split.count > 1 {
+            spread / (split.count - 1) as f32
+        } else {
+            0.0
+        };
+        let first = -step * (split.count.saturating_sub(1)) as f32 / 2.0;
+
+        for i in 0..split.count {
+            let direction = Vec2::from_angle(first + step * i as f32).rotate(bullet.direction);
+            behaviors.insert(&mut bullets.spawn(Bullet {
+                position: bullet.position,
+                direction,
+                speed: split.speed,
+                age: 0.0,
+            }));
+        }
+    }
+}
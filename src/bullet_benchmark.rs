@@ -0,0 +1,374 @@
+use std::{
+    f32::consts::TAU,
+    fs::File,
+    io::{BufWriter, Write},
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use bevy::{app::AppExit, ecs::entity::Entities, prelude::*, time::TimeSystem};
+use rand::Rng;
+
+use crate::{
+    bullet_pattern::{BulletPattern, Speed},
+    bullets::{ArenaBounds, Bullet, BulletArena, BulletSet, Bullets},
+    simulation::SimulationRng,
+};
+
+/// Keeps a growing number of bullets alive along [`BulletBenchmarkPlugin::steps`], and records
+/// once a second how long frames and fixed updates take, and how many entities and assets there
+/// are, to a CSV file. Exits after the last step with a summary of each.
+///
+/// Bullets are spawned all over the arena, going every which way, from [`SimulationRng`].
+/// Steps follow real time, so a simulation that can't keep up doesn't stretch the benchmark.
+pub struct BulletBenchmarkPlugin {
+    pub steps: Vec<BenchmarkStep>,
+    pub path: PathBuf,
+}
+
+impl Default for BulletBenchmarkPlugin {
+    fn default() -> Self {
+        let step = |bullets, seconds| BenchmarkStep { bullets, seconds };
+
+        Self {
+            steps: vec![
+                step(0, 3.0),
+                step(1_000, 5.0),
+                step(5_000, 5.0),
+                step(10_000, 5.0),
+                step(20_000, 5.0),
+                step(50_000, 5.0),
+                step(100_000, 5.0),
+            ],
+            path: PathBuf::from("benchmarks").join(format!(
+                "bullet-hell_{}.csv",
+                chrono::Local::now().format("%Y-%m-%d_%H-%M-%S")
+            )),
+        }
+    }
+}
+
+impl Plugin for BulletBenchmarkPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Benchmark {
+            steps: self.steps.clone(),
+            path: self.path.clone(),
+            csv: None,
+            started: None,
+            target: 0,
+            second: default(),
+            seconds_written: 0,
+            steps_measured: vec![default(); self.steps.len()],
+            fixed_started: None,
+        })
+        .add_systems(First, measure_frame.after(TimeSystem))
+        .add_systems(FixedFirst, start_fixed_timer)
+        .add_systems(FixedLast, stop_fixed_timer)
+        .add_systems(FixedUpdate, keep_bullets_alive.in_set(BulletSet::Spawn));
+    }
+}
+
+/// Ramps linearly from the previous step's bullets to `bullets` during the first half of
+/// `seconds`, and holds for the rest
+#[derive(Debug, Clone, Copy)]
+pub struct BenchmarkStep {
+    pub bullets: u32,
+    pub seconds: f32,
+}
+
+#[derive(Debug, Resource)]
+struct Benchmark {
+    steps: Vec<BenchmarkStep>,
+    path: PathBuf,
+    csv: Option<BufWriter<File>>,
+
+    /// Real time when the first frame was measured
+    started: Option<Duration>,
+
+    /// Bullets to keep alive
+    target: u32,
+
+    /// The second being measured
+    second: Measured,
+    /// Whole seconds written to the CSV
+    seconds_written: u32,
+    /// The second halves of each step
+    steps_measured: Vec<Measured>,
+
+    fixed_started: Option<Instant>,
+}
+
+impl Benchmark {
+    /// Which step, the bullets to keep alive, and whether they're done ramping, `seconds` in
+    fn step_at(&self, seconds: f32) -> Option<(usize, u32, bool)> {
+        let mut start = 0.0;
+        let mut previous = 0;
+
+        for (i, step) in self.steps.iter().enumerate() {
+            let t = (seconds - start) / step.seconds;
+            if t < 1.0 {
+                let ramp = (t * 2.0).min(1.0);
+                let bullets = previous as f32 + (step.bullets as f32 - previous as f32) * ramp;
+                return Some((i, bullets as u32, t >= 0.5));
+            }
+            start += step.seconds;
+            previous = step.bullets;
+        }
+
+        None
+    }
+
+    fn write_row(&mut self, row: &str) {
+        if self.csv.is_none() {
+            let file = self
+                .path
+                .parent()
+                .map_or(Ok(()), std::fs::create_dir_all)
+                .and_then(|()| File::create(&self.path));
+            match file {
+                Ok(file) => {
+                    let mut csv = BufWriter::new(file);
+                    let _ = writeln!(csv, "{}", Measured::HEADER);
+                    self.csv = Some(csv);
+                }
+                Err(e) => {
+                    error!("Could not write benchmark to {}: {e}", self.path.display());
+                    return;
+                }
+            }
+        }
+
+        if let Some(csv) = &mut self.csv {
+            if let Err(e) = writeln!(csv, "{row}").and_then(|()| csv.flush()) {
+                error!("Could not write benchmark to {}: {e}", self.path.display());
+            }
+        }
+    }
+}
+
+/// Sums over some time
+#[derive(Debug, Clone, Default)]
+struct Measured {
+    frames: u32,
+    frame_time: Duration,
+    max_frame_time: Duration,
+
+    fixed_updates: u32,
+    fixed_time: Duration,
+    max_fixed_time: Duration,
+
+    bullets: u64,
+    target_bullets: u64,
+}
+
+impl Measured {
+    const HEADER: &'static str = "second,target_bullets,bullets,frames,frame_ms,max_frame_ms,\
+        fixed_updates,fixed_update_ms,max_fixed_update_ms,entities,meshes,images,materials,\
+        scenes,patterns";
+
+    fn frame_ms(&self) -> f64 {
+        self.frame_time.as_secs_f64() * 1000.0 / self.frames.max(1) as f64
+    }
+
+    fn fixed_ms(&self) -> f64 {
+        self.fixed_time.as_secs_f64() * 1000.0 / self.fixed_updates.max(1) as f64
+    }
+
+    fn bullets(&self) -> u64 {
+        self.bullets / self.frames.max(1) as u64
+    }
+
+    fn target_bullets(&self) -> u64 {
+        self.target_bullets / self.frames.max(1) as u64
+    }
+}
+
+/// Asset counts, for what there is of them
+#[derive(bevy::ecs::system::SystemParam)]
+struct AssetCounts<'w> {
+    meshes: Option<Res<'w, Assets<Mesh>>>,
+    images: Option<Res<'w, Assets<Image>>>,
+    materials: Option<Res<'w, Assets<StandardMaterial>>>,
+    scenes: Option<Res<'w, Assets<Scene>>>,
+    patterns: Option<Res<'w, Assets<BulletPattern>>>,
+}
+
+impl AssetCounts<'_> {
+    fn csv(&self) -> String {
+        [
+            self.meshes.as_ref().map(|assets| assets.len()),
+            self.images.as_ref().map(|assets| assets.len()),
+            self.materials.as_ref().map(|assets| assets.len()),
+            self.scenes.as_ref().map(|assets| assets.len()),
+            self.patterns.as_ref().map(|assets| assets.len()),
+        ]
+        .map(|count| count.unwrap_or(0).to_string())
+        .join(",")
+    }
+}
+
+fn measure_frame(
+    time: Res<Time<Real>>,
+    entities: &Entities,
+    assets: AssetCounts,
+    bullets: Query<(), With<Bullet>>,
+    mut benchmark: ResMut<Benchmark>,
+    mut exit: EventWriter<AppExit>,
+) {
+    let now = time.elapsed();
+    let started = *benchmark.started.get_or_insert(now);
+    let seconds = (now - started).as_secs_f32();
+
+    let Some((_, target, _)) = benchmark.step_at(seconds) else {
+        summarize(&benchmark);
+        exit.send(AppExit);
+        return;
+    };
+    benchmark.target = target;
+
+    let second = &mut benchmark.second;
+    second.frames += 1;
+    second.frame_time += time.delta();
+    second.max_frame_time = second.max_frame_time.max(time.delta());
+    second.bullets += bullets.iter().count() as u64;
+    second.target_bullets += target as u64;
+
+    // Once a whole second is measured
+    let whole_seconds = seconds as u32;
+    if whole_seconds == benchmark.seconds_written {
+        return;
+    }
+    benchmark.seconds_written = whole_seconds;
+
+    let second = std::mem::take(&mut benchmark.second);
+    let row = format!(
+        "{whole_seconds},{},{},{},{:.3},{:.3},{},{:.3},{:.3},{},{}",
+        second.target_bullets(),
+        second.bullets(),
+        second.frames,
+        second.frame_ms(),
+        second.max_frame_time.as_secs_f64() * 1000.0,
+        second.fixed_updates,
+        second.fixed_ms(),
+        second.max_fixed_time.as_secs_f64() * 1000.0,
+        entities.len(),
+        assets.csv(),
+    );
+    benchmark.write_row(&row);
+
+    // Only seconds after ramping up count towards the summary
+    if let Some((step, _, true)) = benchmark.step_at(whole_seconds as f32 - 1.0) {
+        let measured = &mut benchmark.steps_measured[step];
+        measured.frames += second.frames;
+        measured.frame_time += second.frame_time;
+        measured.max_frame_time = measured.max_frame_time.max(second.max_frame_time);
+        measured.fixed_updates += second.fixed_updates;
+        measured.fixed_time += second.fixed_time;
+        measured.max_fixed_time = measured.max_fixed_time.max(second.max_fixed_time);
+        measured.bullets += second.bullets;
+        measured.target_bullets += second.target_bullets;
+    }
+}
+
+fn summarize(benchmark: &Benchmark) {
+    info!("Benchmark done, see {}", benchmark.path.display());
+
+    for (step, measured) in benchmark.steps.iter().zip(&benchmark.steps_measured) {
+        if measured.frames == 0 {
+            continue;
+        }
+        info!(
+            "{:>7} bullets: {:>6.1} fps, {:>7.3} ms/frame (max {:.3}), {:>7.3} ms/fixed update (max {:.3})",
+            step.bullets,
+            1000.0 / measured.frame_ms(),
+            measured.frame_ms(),
+            measured.max_frame_time.as_secs_f64() * 1000.0,
+            measured.fixed_ms(),
+            measured.max_fixed_time.as_secs_f64() * 1000.0,
+        );
+    }
+}
+
+fn start_fixed_timer(mut benchmark: ResMut<Benchmark>) {
+    benchmark.fixed_started = Some(Instant::now());
+}
+
+fn stop_fixed_timer(mut benchmark: ResMut<Benchmark>) {
+    let Some(started) = benchmark.fixed_started.take() else {
+        return;
+    };
+
+    let elapsed = started.elapsed();
+    let second = &mut benchmark.second;
+    second.fixed_updates += 1;
+    second.fixed_time += elapsed;
+    second.max_fixed_time = second.max_fixed_time.max(elapsed);
+}
+
+fn keep_bullets_alive(
+    benchmark: Res<Benchmark>,
+    arena: Res<BulletArena>,
+    mut rng: ResMut<SimulationRng>,
+    existing: Query<(), With<Bullet>>,
+    mut bullets: Bullets,
+) {
+    let (center, radius) = match arena.bounds {
+        ArenaBounds::Circle { center, radius } => (center, radius),
+        ArenaBounds::Rect(rect) => (rect.center(), rect.half_size().min_element()),
+    };
+
+    let missing = benchmark
+        .target
+        .saturating_sub(existing.iter().count() as u32);
+    for _ in 0..missing {
+        let position =
+            center + Vec2::from_angle(rng.gen::<f32>() * TAU) * radius * rng.gen::<f32>().sqrt();
+        bullets.spawn(Bullet {
+            position,
+            direction: Vec2::from_angle(rng.gen::<f32>() * TAU),
+            speed: Speed::Constant(rng.gen_range(0.2..1.0)),
+            age: 0.0,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn benchmark(steps: &[(u32, f32)]) -> Benchmark {
+        let plugin = BulletBenchmarkPlugin {
+            steps: steps
+                .iter()
+                .map(|&(bullets, seconds)| BenchmarkStep { bullets, seconds })
+                .collect(),
+            path: default(),
+        };
+
+        let mut app = App::new();
+        plugin.build(&mut app);
+        app.world.remove_resource::<Benchmark>().unwrap()
+    }
+
+    #[test]
+    fn steps_ramp_then_hold() {
+        let ramp = benchmark(&[(0, 2.0), (100, 2.0), (50, 4.0)]);
+
+        assert_eq!(ramp.step_at(0.0), Some((0, 0, false)));
+        assert_eq!(ramp.step_at(1.0), Some((0, 0, true)));
+
+        // Ramping up from the previous step during the first half
+        assert_eq!(ramp.step_at(2.0), Some((1, 0, false)));
+        assert_eq!(ramp.step_at(2.5), Some((1, 50, false)));
+        assert_eq!(ramp.step_at(3.0), Some((1, 100, true)));
+        assert_eq!(ramp.step_at(3.9), Some((1, 100, true)));
+
+        // And down
+        assert_eq!(ramp.step_at(4.0), Some((2, 100, false)));
+        assert_eq!(ramp.step_at(5.0), Some((2, 75, false)));
+        assert_eq!(ramp.step_at(6.0), Some((2, 50, true)));
+
+        assert_eq!(ramp.step_at(8.0), None);
+        assert_eq!(benchmark(&[]).step_at(0.0), None);
+    }
+}
@@ -0,0 +1,403 @@
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::{
+    bullet_pattern::{BulletEmitter, BulletPatternSet},
+    bullets::{Bullet, BulletArena, BulletSet, Bullets, DespawnReason},
+};
+
+/// Makes bullet-hell a game: The [`Player`] dodges [`Bullet`]s, loses lives when hit,
+/// and scores by grazing them. Once out of lives it's game over until restarted.
+///
+/// Everything happens in `FixedUpdate`, driven only by [`PlayerInput`], so a run plays out
+/// the same given the same input.
+///
+/// Moves with arrows/WASD or the left stick, focuses (slows down) with shift or the right
+/// trigger, and restarts with R or start. Movement is relative to the [`ArenaCamera`], if any.
+pub struct BulletGamePlugin;
+
+impl Plugin for BulletGamePlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<BulletGameParams>()
+            .init_resource::<BulletGameParams>()
+            .init_resource::<BulletGame>()
+            .init_resource::<PlayerInput>()
+            .init_resource::<BulletGrid>()
+            .add_event::<BulletGameEvent>()
+            .configure_sets(
+                FixedUpdate,
+                (
+                    (GameSet::Input, GameSet::Player)
+                        .chain()
+                        .before(BulletPatternSet)
+                        .before(BulletSet::Spawn),
+                    GameSet::Collide
+                        .after(BulletSet::Move)
+                        .before(BulletSet::Despawn),
+                ),
+            )
+            .add_systems(
+                FixedUpdate,
+                (
+                    read_player_input.in_set(GameSet::Input),
+                    (restart, move_player).chain().in_set(GameSet::Player),
+                    (rebuild_bullet_grid, collide)
+                        .chain()
+                        .in_set(GameSet::Collide),
+                ),
+            )
+            .add_systems(Update, place_players);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, SystemSet)]
+pub enum GameSet {
+    /// [`PlayerInput`] is written here, replace this to drive the player some other way
+    Input,
+
+    /// Restarts and moves the [`Player`], before emitters fire
+    Player,
+
+    Collide,
+}
+
+#[derive(Debug, Clone, Resource, Reflect)]
+#[reflect(Resource)]
+pub struct BulletGameParams {
+    /// Units per second
+    pub speed: f32,
+    /// Units per second while focused
+    pub focus_speed: f32,
+
+    /// The player only gets hit by bullets this close, much smaller than what it looks like
+    pub hitbox_radius: f32,
+    pub bullet_radius: f32,
+
+    /// Bullets passing this close score, once each
+    pub graze_radius: f32,
+    pub graze_score: u64,
+
+    pub lives: u32,
+    /// Seconds the player can't be hit after being hit
+    pub invulnerability: f32,
+
+    /// Where the player starts, on the arena plane
+    pub start: Vec2,
+}
+
+impl Default for BulletGameParams {
+    fn default() -> Self {
+        Self {
+            speed: 2.0,
+            focus_speed: 0.8,
+            hitbox_radius: 0.02,
+            bullet_radius: 0.04,
+            graze_radius: 0.2,
+            graze_score: 10,
+            lives: 3,
+            invulnerability: 2.0,
+            start: Vec2::new(0.0, 2.5),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GamePhase {
+    Playing,
+    GameOver,
+}
+
+/// The state of the current run
+#[derive(Debug, Clone, Resource)]
+pub struct BulletGame {
+    pub phase: GamePhase,
+    pub lives: u32,
+    pub score: u64,
+    pub grazes: u32,
+
+    /// Seconds of invulnerability left
+    pub invulnerable: f32,
+
+    /// Seconds since the run started
+    pub time: f32,
+
+    /// Restart on press, not while held
+    restart_held: bool,
+}
+
+impl Default for BulletGame {
+    fn default() -> Self {
+        Self::new(&default())
+    }
+}
+
+impl BulletGame {
+    fn new(params: &BulletGameParams) -> Self {
+        Self {
+            phase: GamePhase::Playing,
+            lives: params.lives,
+            score: 0,
+            grazes: 0,
+            invulnerable: 0.0,
+            time: 0.0,
+            restart_held: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Event)]
+pub enum BulletGameEvent {
+    Hit,
+    Graze,
+    GameOver,
+    Restarted,
+}
+
+/// What the player wants this tick
+#[derive(Debug, Clone, Copy, Default, PartialEq, Resource)]
+pub struct PlayerInput {
+    /// On the arena plane, at most length 1
+    pub movement: Vec2,
+    pub focus: bool,
+    pub restart: bool,
+}
+
+/// Spawn one, e.g. with a model as its child. Its [`Transform`] follows [`Player::position`].
+#[derive(Debug, Clone, Default, Component)]
+pub struct Player {
+    pub position: Vec2,
+
+    /// Units per second on the arena plane, e.g. to face where it's going
+    pub velocity: Vec2,
+
+    pub focused: bool,
+}
+
+/// Player movement is relative to how this camera sees the arena
+#[derive(Debug, Clone, Copy, Component)]
+pub struct ArenaCamera;
+
+/// Bullets by where they are, rebuilt every tick after they've moved
+#[derive(Debug, Default, Resource)]
+pub struct BulletGrid {
+    cell_size: f32,
+    cells: HashMap<IVec2, Vec<(Entity, Vec2)>>,
+}
+
+impl BulletGrid {
+    fn cell(&self, position: Vec2) -> IVec2 {
+        (position / self.cell_size).floor().as_ivec2()
+    }
+
+    /// Bullets which may be within a cell size of `position`, and some further away
+    pub fn near(&self, position: Vec2) -> impl Iterator<Item = &(Entity, Vec2)> {
+        let center = self.cell(position);
+
+        (-1..=1)
+            .flat_map(|x| (-1..=1).map(move |y| IVec2::new(x, y)))
+            .filter_map(move |offset| self.cells.get(&(center + offset)))
+            .flatten()
+    }
+}
+
+/// Marks bullets which already scored
+#[derive(Debug, Clone, Copy, Component)]
+struct Grazed;
+
+fn read_player_input(
+    keys: Res<ButtonInput<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    gamepad_buttons: Res<ButtonInput<GamepadButton>>,
+    gamepad_axes: Res<Axis<GamepadAxis>>,
+    arena: Res<BulletArena>,
+    cameras: Query<&GlobalTransform, With<ArenaCamera>>,
+    mut input: ResMut<PlayerInput>,
+) {
+    let key_axis = |negative: [KeyCode; 2], positive: [KeyCode; 2]| {
+        keys.any_pressed(positive) as i32 as f32 - keys.any_pressed(negative) as i32 as f32
+    };
+
+    // Right and up on screen
+    let mut movement = Vec2::new(
+        key_axis(
+            [KeyCode::ArrowLeft, KeyCode::KeyA],
+            [KeyCode::ArrowRight, KeyCode::KeyD],
+        ),
+        key_axis(
+            [KeyCode::ArrowDown, KeyCode::KeyS],
+            [KeyCode::ArrowUp, KeyCode::KeyW],
+        ),
+    );
+    let mut focus = keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    let mut restart = keys.pressed(KeyCode::KeyR);
+
+    for gamepad in gamepads.iter() {
+        let axis = |axis_type| {
+            gamepad_axes
+                .get(GamepadAxis::new(gamepad, axis_type))
+                .unwrap_or(0.0)
+        };
+        movement += Vec2::new(
+            axis(GamepadAxisType::LeftStickX),
+            axis(GamepadAxisType::LeftStickY),
+        );
+
+        let button = |button_type| GamepadButton::new(gamepad, button_type);
+        focus |= gamepad_buttons.pressed(button(GamepadButtonType::RightTrigger))
+            || gamepad_buttons.pressed(button(GamepadButtonType::RightTrigger2));
+        restart |= gamepad_buttons.pressed(button(GamepadButtonType::Start));
+    }
+
+    // Onto the arena, as the camera sees it
+    if let Ok(camera) = cameras.get_single() {
+        let right = arena.plane.project(camera.right()).normalize_or_zero();
+        let up = arena.plane.project(camera.up()).normalize_or_zero();
+        movement = right * movement.x + up * movement.y;
+    }
+
+    *input = PlayerInput {
+        movement: movement.clamp_length_max(1.0),
+        focus,
+        restart,
+    };
+}
+
+fn restart(
+    params: Res<BulletGameParams>,
+    input: Res<PlayerInput>,
+    mut game: ResMut<BulletGame>,
+    mut players: Query<&mut Player>,
+    mut emitters: Query<&mut BulletEmitter>,
+    mut bullets: Bullets,
+    existing: Query<(Entity, &Bullet)>,
+    mut events: EventWriter<BulletGameEvent>,
+) {
+    let pressed = input.restart && !game.restart_held;
+    game.restart_held = input.restart;
+    if !pressed {
+        return;
+    }
+
+    *game = BulletGame::new(&params);
+
+    for mut player in &mut players {
+        *player = Player {
+            position: params.start,
+            ..default()
+        };
+    }
+    for mut emitter in &mut emitters {
+        emitter.elapsed = 0.0;
+    }
+    for (entity, bullet) in &existing {
+        bullets.despawn(entity, bullet.position, DespawnReason::Cleared);
+    }
+
+    info!("Restarted");
+    events.send(BulletGameEvent::Restarted);
+}
+
+fn move_player(
+    time: Res<Time>,
+    params: Res<BulletGameParams>,
+    arena: Res<BulletArena>,
+    input: Res<PlayerInput>,
+    mut game: ResMut<BulletGame>,
+    mut players: Query<&mut Player>,
+) {
+    let dt = time.delta_seconds();
+
+    if game.phase == GamePhase::Playing {
+        game.time += dt;
+        game.invulnerable = (game.invulnerable - dt).max(0.0);
+    }
+
+    for mut player in &mut players {
+        if game.phase != GamePhase::Playing {
+            player.velocity = Vec2::ZERO;
+            continue;
+        }
+
+        let speed = if input.focus {
+            params.focus_speed
+        } else {
+            params.speed
+        };
+
+        let previous = player.position;
+        player.position = arena.bounds.clamp(
+            player.position + input.movement * speed * dt,
+            params.hitbox_radius,
+        );
+        player.velocity = (player.position - previous) / dt.max(f32::EPSILON);
+        player.focused = input.focus;
+    }
+}
+
+fn rebuild_bullet_grid(
+    params: Res<BulletGameParams>,
+    mut grid: ResMut<BulletGrid>,
+    bullets: Query<(Entity, &Bullet)>,
+) {
+    grid.cell_size = params.graze_radius.max(0.01);
+
+    // Keep allocations around between ticks
+    grid.cells.values_mut().for_each(Vec::clear);
+
+    for (entity, bullet) in &bullets {
+        let cell = grid.cell(bullet.position);
+        grid.cells
+            .entry(cell)
+            .or_default()
+            .push((entity, bullet.position));
+    }
+}
+
+fn collide(
+    mut commands: Commands,
+    params: Res<BulletGameParams>,
+    grid: Res<BulletGrid>,
+    mut game: ResMut<BulletGame>,
+    players: Query<&Player>,
+    grazed: Query<(), With<Grazed>>,
+    mut bullets: Bullets,
+    mut events: EventWriter<BulletGameEvent>,
+) {
+    if game.phase != GamePhase::Playing {
+        return;
+    }
+
+    let hit_distance = params.hitbox_radius + params.bullet_radius;
+
+    for player in &players {
+        for &(entity, position) in grid.near(player.position) {
+            let distance = position.distance(player.position);
+
+            if distance < hit_distance && game.invulnerable <= 0.0 {
+                bullets.despawn(entity, position, DespawnReason::Hit);
+                game.lives = game.lives.saturating_sub(1);
+                game.invulnerable = params.invulnerability;
+                events.send(BulletGameEvent::Hit);
+
+                if game.lives == 0 {
+                    game.phase = GamePhase::GameOver;
+                    info!("Game over, score {}", game.score);
+                    events.send(BulletGameEvent::GameOver);
+                    return;
+                }
+            } else if distance < params.graze_radius && !grazed.contains(entity) {
+                commands.entity(entity).insert(Grazed);
+                game.grazes += 1;
+                game.score += params.graze_score;
+                events.send(BulletGameEvent::Graze);
+            }
+        }
+    }
+}
+
+/// Players' transforms follow them on the arena plane, keeping their height
+fn place_players(arena: Res<BulletArena>, mut players: Query<(&mut Transform, &Player)>) {
+    for (mut transform, player) in &mut players {
+        let height = arena.plane.height(transform.translation);
+        transform.translation = arena.plane.unproject(player.position, height);
+    }
+}
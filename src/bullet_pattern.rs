@@ -0,0 +1,649 @@
+use std::f32::consts::TAU;
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    prelude::*,
+    utils::BoxedFuture,
+};
+use serde::Deserialize;
+
+use crate::bullet_behaviors::BulletBehaviors;
+
+/// Fires bullets from [`BulletEmitter`]s according to their [`BulletPattern`], in `FixedUpdate`.
+///
+/// Patterns are RON files ending in `.pattern.ron`, see `assets/patterns/` for examples.
+/// Saving a pattern while the app runs restarts the emitters using it.
+///
+/// This only decides where bullets go, spawning them on [`BulletFired`] is up to the app.
+pub struct BulletPatternPlugin;
+
+impl Plugin for BulletPatternPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<BulletPattern>()
+            .register_asset_loader(BulletPatternLoader)
+            .add_event::<BulletFired>()
+            .add_systems(Update, restart_reloaded_emitters)
+            .add_systems(FixedUpdate, fire_emitters.in_set(BulletPatternSet));
+    }
+}
+
+/// Where [`BulletFired`] events are sent, spawn bullets after this
+#[derive(Debug, Clone, PartialEq, Eq, Hash, SystemSet)]
+pub struct BulletPatternSet;
+
+/// A pattern to fire bullets in, loaded from a `.pattern.ron` file
+#[derive(Debug, Clone, Asset, TypePath, Deserialize, Deref)]
+#[serde(transparent)]
+pub struct BulletPattern(pub Pattern);
+
+/// Angles are in degrees, counterclockwise from the emitter's X axis.
+/// Times are in seconds since the (sub-)pattern started.
+#[derive(Debug, Clone, Deserialize)]
+pub enum Pattern {
+    /// `count` bullets evenly around a circle, once
+    Ring { count: u32, speed: Speed },
+
+    /// `count` bullets evenly across `spread` degrees centered on the X axis, once
+    Arc {
+        count: u32,
+        spread: f32,
+        speed: Speed,
+    },
+
+    /// `count` bullets at scattered angles within `spread` degrees, once.
+    /// Each goes between `1 - speed_spread` and 1 times `speed`.
+    Burst {
+        count: u32,
+        spread: f32,
+        speed: Speed,
+        #[serde(default)]
+        speed_spread: f32,
+    },
+
+    /// `rate` bullets per second from each of `arms` arms, turning `turn` degrees per second, forever
+    Spiral {
+        arms: u32,
+        rate: f32,
+        turn: f32,
+        speed: Speed,
+    },
+
+    /// Nothing, for this long
+    Wait(f32),
+
+    /// Cuts `pattern` off after `duration`
+    For {
+        duration: f32,
+        pattern: Box<Pattern>,
+    },
+
+    /// Starts `pattern` every `interval`, `times` times or forever.
+    /// A `pattern` that never ends is cut off when the next one starts.
+    Every {
+        interval: f32,
+        #[serde(default)]
+        times: Option<u32>,
+        pattern: Box<Pattern>,
+    },
+
+    /// One after the other. Anything after a pattern that never ends never starts.
+    Sequence(Vec<Pattern>),
+
+    /// All at once
+    Parallel(Vec<Pattern>),
+
+    /// Turns `pattern` by `angle`, plus `speed` degrees per second
+    Rotate {
+        #[serde(default)]
+        angle: f32,
+        #[serde(default)]
+        speed: f32,
+        pattern: Box<Pattern>,
+    },
+
+    /// Gives bullets fired by `pattern` `behaviors`, unless an inner `Behave` already did
+    Behave {
+        behaviors: BulletBehaviors,
+        pattern: Box<Pattern>,
+    },
+}
+
+/// How fast a bullet goes, in units per second, depending on its age.
+/// Either a number or `(from: 2.0, to: 0.5, over: 1.0)`.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum Speed {
+    Constant(f32),
+
+    /// Linearly from `from` to `to` during the first `over` seconds
+    Ramp {
+        from: f32,
+        to: f32,
+        over: f32,
+    },
+}
+
+impl Speed {
+    pub fn at(&self, age: f32) -> f32 {
+        match *self {
+            Speed::Constant(speed) => speed,
+            Speed::Ramp { from, to, over } => {
+                let t = if over > 0.0 {
+                    (age / over).clamp(0.0, 1.0)
+                } else {
+                    1.0
+                };
+                from + (to - from) * t
+            }
+        }
+    }
+
+    fn scaled(self, factor: f32) -> Self {
+        match self {
+            Speed::Constant(speed) => Speed::Constant(speed * factor),
+            Speed::Ramp { from, to, over } => Speed::Ramp {
+                from: from * factor,
+                to: to * factor,
+                over,
+            },
+        }
+    }
+}
+
+/// A bullet fired by a [`Pattern`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Shot {
+    /// When, in pattern time
+    pub time: f32,
+
+    /// In radians
+    pub angle: f32,
+
+    pub speed: Speed,
+
+    pub behaviors: BulletBehaviors,
+}
+
+impl Pattern {
+    /// How long until it's done firing, `None` if never
+    pub fn duration(&self) -> Option<f32> {
+        match self {
+            Pattern::Ring { .. } | Pattern::Arc { .. } | Pattern::Burst { .. } => Some(0.0),
+            Pattern::Spiral { .. } => None,
+            Pattern::Wait(duration) | Pattern::For { duration, .. } => Some(*duration),
+            Pattern::Every {
+                interval,
+                times,
+                pattern,
+            } => {
+                let times = (*times)?;
+                if times == 0 {
+                    return Some(0.0);
+                }
+                let last_start = (times - 1) as f32 * interval;
+                Some(last_start + pattern.duration()?)
+            }
+            Pattern::Sequence(patterns) => patterns.iter().map(Pattern::duration).sum(),
+            Pattern::Parallel(patterns) => patterns
+                .iter()
+                .map(Pattern::duration)
+                .try_fold(0.0, |longest: f32, duration| Some(longest.max(duration?))),
+            Pattern::Rotate { pattern, .. } | Pattern::Behave { pattern, .. } => pattern.duration(),
+        }
+    }
+
+    /// Adds the shots fired from `from` up to (not including) `to` to `shots`.
+    /// `seed` varies scattered patterns like [`Pattern::Burst`], the same seed fires the same shots.
+    pub fn fire(&self, from: f32, to: f32, seed: u32, shots: &mut Vec<Shot>) {
+        if to <= from {
+            return;
+        }
+
+        let once = from <= 0.0 && 0.0 < to;
+
+        match self {
+            Pattern::Ring { count, speed } => {
+                if once {
+                    for i in 0..*count {
+                        shots.push(Shot {
+                            time: 0.0,
+                            angle: i as f32 / *count as f32 * TAU,
+                            speed: *speed,
+                            behaviors: default(),
+                        });
+                    }
+                }
+            }
+            Pattern::Arc {
+                count,
+                spread,
+                speed,
+            } => {
+                if once {
+                    let spread = spread.to_radians();
+                    for i in 0..*count {
+                        let t = if *count > 1 {
+                            i as f32 / (*count - 1) as f32
+                        } else {
+                            0.5
+                        };
+                        shots.push(Shot {
+                            time: 0.0,
+                            angle: (t - 0.5) * spread,
+                            speed: *speed,
+                            behaviors: default(),
+                        });
+                    }
+                }
+            }
+            Pattern::Burst {
+                count,
+                spread,
+                speed,
+                speed_spread,
+            } => {
+                if once {
+                    let spread = spread.to_radians();
+                    for i in 0..*count {
+                        let angle = (scatter(seed, 2 * i) - 0.5) * spread;
+                        let factor = 1.0 - scatter(seed, 2 * i + 1) * speed_spread;
+                        shots.push(Shot {
+                            time: 0.0,
+                            angle,
+                            speed: speed.scaled(factor),
+                            behaviors: default(),
+                        });
+                    }
+                }
+            }
+            Pattern::Spiral {
+                arms,
+                rate,
+                turn,
+                speed,
+            } => {
+                if *rate <= 0.0 {
+                    return;
+                }
+                let first = (from.max(0.0) * rate).ceil() as u32;
+                let end = (to * rate).ceil().max(0.0) as u32;
+                for n in first..end {
+                    let time = n as f32 / rate;
+                    for arm in 0..*arms {
+                        shots.push(Shot {
+                            time,
+                            angle: (time * turn).to_radians() + arm as f32 / *arms as f32 * TAU,
+                            speed: *speed,
+                            behaviors: default(),
+                        });
+                    }
+                }
+            }
+            Pattern::Wait(_) => {}
+            Pattern::For { duration, pattern } => {
+                pattern.fire(from, to.min(*duration), seed, shots)
+            }
+            Pattern::Every {
+                interval,
+                times,
+                pattern,
+            } => {
+                if *interval <= 0.0 {
+                    return;
+                }
+                // Only repetitions that could still be firing, and have started by `to`
+                let duration = pattern.duration();
+                let mut first = ((from - duration.unwrap_or(*interval)) / interval)
+                    .floor()
+                    .max(0.0) as u32;
+                let mut end = (to / interval).ceil().max(0.0) as u32;
+                if let Some(times) = *times {
+                    end = end.min(times);
+                    // The last one of those that never end goes on forever
+                    if duration.is_none() {
+                        first = first.min(times.saturating_sub(1));
+                    }
+                }
+                for n in first..end {
+                    let start = n as f32 * interval;
+                    let cut_off = duration.is_none() && times.is_none_or(|times| n + 1 < times);
+                    let to = if cut_off {
+                        to.min(start + interval)
+                    } else {
+                        to
+                    };
+                    fire_shifted(pattern, start, from, to, mix(seed, n), shots);
+                }
+            }
+            Pattern::Sequence(patterns) => {
+                let mut start = 0.0;
+                for (i, pattern) in (0..).zip(patterns) {
+                    if start >= to {
+                        break;
+                    }
+                    fire_shifted(pattern, start, from, to, mix(seed, i), shots);
+                    let Some(duration) = pattern.duration() else {
+                        break;
+                    };
+                    start += duration;
+                }
+            }
+            Pattern::Parallel(patterns) => {
+                for (i, pattern) in (0..).zip(patterns) {
+                    pattern.fire(from, to, mix(seed, i), shots);
+                }
+            }
+            Pattern::Rotate {
+                angle,
+                speed,
+                pattern,
+            } => {
+                let first = shots.len();
+                pattern.fire(from, to, seed, shots);
+                for shot in &mut shots[first..] {
+                    shot.angle += (angle + speed * shot.time).to_radians();
+                }
+            }
+            Pattern::Behave { behaviors, pattern } => {
+                let first = shots.len();
+                pattern.fire(from, to, seed, shots);
+                for shot in &mut shots[first..] {
+                    shot.behaviors = shot.behaviors.or(*behaviors);
+                }
+            }
+        }
+    }
+}
+
+/// Fires `pattern` as if it started at `start`
+fn fire_shifted(
+    pattern: &Pattern,
+    start: f32,
+    from: f32,
+    to: f32,
+    seed: u32,
+    shots: &mut Vec<Shot>,
+) {
+    let first = shots.len();
+    pattern.fire(from - start, to - start, seed, shots);
+    for shot in &mut shots[first..] {
+        shot.time += start;
+    }
+}
+
+/// Looks random, but is the same for the same `seed` and `index`, between 0 and 1
+fn scatter(seed: u32, index: u32) -> f32 {
+    mix(seed, index) as f32 / u32::MAX as f32
+}
+
+/// A different seed for each `index`, e.g. for each repetition of a sub-pattern
+fn mix(seed: u32, index: u32) -> u32 {
+    let mut x = index.wrapping_mul(0x9E37_79B9) ^ seed.wrapping_mul(0x85EB_CA6B);
+    x ^= x >> 16;
+    x = x.wrapping_mul(0x7FEB_352D);
+    x ^= x >> 15;
+    x
+}
+
+/// Fires bullets following a [`BulletPattern`], from its [`Transform`]
+#[derive(Debug, Clone, Component)]
+pub struct BulletEmitter {
+    pub pattern: Handle<BulletPattern>,
+
+    /// Seconds since the pattern started
+    pub elapsed: f32,
+
+    /// Varies scattered patterns, see [`Pattern::fire`]
+    pub seed: u32,
+}
+
+impl BulletEmitter {
+    pub fn new(pattern: Handle<BulletPattern>) -> Self {
+        Self {
+            pattern,
+            elapsed: 0.0,
+            seed: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Event)]
+pub struct BulletFired {
+    pub emitter: Entity,
+
+    /// In the emitter's XY plane, length 1
+    pub direction: Vec2,
+
+    pub speed: Speed,
+
+    /// Seconds since it was fired, as it may have been fired between fixed updates
+    pub age: f32,
+
+    pub behaviors: BulletBehaviors,
+}
+
+fn fire_emitters(
+    time: Res<Time>,
+    patterns: Res<Assets<BulletPattern>>,
+    mut emitters: Query<(Entity, &mut BulletEmitter)>,
+    mut fired: EventWriter<BulletFired>,
+    mut shots: Local<Vec<Shot>>,
+) {
+    for (emitter, mut bullet_emitter) in &mut emitters {
+        let Some(pattern) = patterns.get(&bullet_emitter.pattern) else {
+            continue;
+        };
+
+        let from = bullet_emitter.elapsed;
+        let to = from + time.delta_seconds();
+        bullet_emitter.elapsed = to;
+
+        shots.clear();
+        pattern.fire(from, to, bullet_emitter.seed, &mut shots);
+
+        fired.send_batch(shots.iter().map(|shot| BulletFired {
+            emitter,
+            direction: Vec2::from_angle(shot.angle),
+            speed: shot.speed,
+            age: to - shot.time,
+            behaviors: shot.behaviors,
+        }));
+    }
+}
+
+fn restart_reloaded_emitters(
+    mut events: EventReader<AssetEvent<BulletPattern>>,
+    mut emitters: Query<&mut BulletEmitter>,
+) {
+    for event in events.read() {
+        let AssetEvent::Modified { id } = event else {
+            continue;
+        };
+
+        info!("Bullet pattern {id} reloaded, restarting its emitters");
+        for mut emitter in &mut emitters {
+            if emitter.pattern.id() == *id {
+                emitter.elapsed = 0.0;
+            }
+        }
+    }
+}
+
+struct BulletPatternLoader;
+
+impl AssetLoader for BulletPatternLoader {
+    type Asset = BulletPattern;
+    type Settings = ();
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<BulletPattern, Self::Error>> {
+        Box::pin(async move {
+            let mut ron = String::new();
+            reader.read_to_string(&mut ron).await?;
+            Ok(ron::from_str(&ron)?)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["pattern.ron"]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pattern(ron: &str) -> Pattern {
+        ron::Options::default()
+            .with_default_extension(ron::extensions::Extensions::IMPLICIT_SOME)
+            .from_str(ron)
+            .unwrap()
+    }
+
+    fn fire(pattern: &Pattern, from: f32, to: f32) -> Vec<Shot> {
+        let mut shots = vec![];
+        pattern.fire(from, to, 7, &mut shots);
+        shots
+    }
+
+    fn times(shots: &[Shot]) -> Vec<f32> {
+        shots.iter().map(|shot| shot.time).collect()
+    }
+
+    #[test]
+    fn ring_fires_once_at_the_start() {
+        let ring = pattern("Ring(count: 8, speed: 1.0)");
+
+        let shots = fire(&ring, 0.0, 0.1);
+        assert_eq!(shots.len(), 8);
+        assert!(shots.iter().all(|shot| shot.time == 0.0));
+        assert!((shots[2].angle - TAU / 4.0).abs() < 1e-6);
+
+        assert!(fire(&ring, 0.1, 0.2).is_empty());
+        assert!(fire(&ring, -0.1, 0.0).is_empty());
+    }
+
+    #[test]
+    fn every_and_sequence_shift_times() {
+        let every = pattern("Every(interval: 0.5, times: 3, pattern: Ring(count: 1, speed: 1.0))");
+        assert_eq!(times(&fire(&every, 0.0, 10.0)), [0.0, 0.5, 1.0]);
+        assert_eq!(times(&fire(&every, 0.25, 0.75)), [0.5]);
+
+        let sequence = pattern(
+            "Sequence([
+                Wait(1.0),
+                Ring(count: 1, speed: 1.0),
+                Wait(0.5),
+                Every(interval: 0.25, times: 2, pattern: Ring(count: 1, speed: 1.0)),
+            ])",
+        );
+        assert_eq!(times(&fire(&sequence, 0.0, 10.0)), [1.0, 1.5, 1.75]);
+    }
+
+    #[test]
+    fn every_cuts_off_never_ending_patterns() {
+        let every = pattern(
+            "Every(interval: 1.0, pattern: Spiral(arms: 1, rate: 10.0, turn: 0.0, speed: 1.0))",
+        );
+
+        // Only the latest repetition is firing, not every one since the start
+        let shots = fire(&every, 10.0, 10.25);
+        assert_eq!(times(&shots), [10.0, 10.1, 10.2]);
+
+        // Except for the last one, which goes on
+        let times_3 = pattern(
+            "Every(interval: 1.0, times: 3, pattern: Spiral(arms: 1, rate: 10.0, turn: 0.0, speed: 1.0))",
+        );
+        assert_eq!(fire(&times_3, 0.0, 3.0).len(), 30);
+        assert_eq!(times(&fire(&times_3, 10.0, 10.25)), [10.0, 10.1, 10.2]);
+    }
+
+    #[test]
+    fn split_windows_fire_the_same_shots() {
+        let pattern = pattern(
+            "Parallel([
+                Rotate(speed: 30.0, pattern: Every(
+                    interval: 0.7,
+                    pattern: Spiral(arms: 3, rate: 13.0, turn: 90.0, speed: 1.0),
+                )),
+                Every(interval: 0.4, pattern: Sequence([
+                    Burst(count: 5, spread: 90.0, speed: 1.0, speed_spread: 0.5),
+                    Wait(0.15),
+                    Arc(count: 3, spread: 30.0, speed: 1.0),
+                ])),
+                For(duration: 1.3, pattern: Spiral(arms: 1, rate: 7.0, turn: 10.0, speed: 1.0)),
+            ])",
+        );
+
+        let sorted = |mut shots: Vec<Shot>| {
+            shots.sort_by(|a, b| a.time.total_cmp(&b.time).then(a.angle.total_cmp(&b.angle)));
+            shots
+        };
+
+        let whole = sorted(fire(&pattern, 0.0, 3.0));
+        let ticks = sorted(
+            (0..192)
+                .flat_map(|tick| fire(&pattern, tick as f32 / 64.0, (tick + 1) as f32 / 64.0))
+                .collect(),
+        );
+
+        assert_eq!(whole.len(), ticks.len());
+        for (whole, tick) in whole.iter().zip(&ticks) {
+            assert!(
+                (whole.time - tick.time).abs() < 1e-4,
+                "{whole:?} != {tick:?}"
+            );
+            assert!(
+                (whole.angle - tick.angle).abs() < 1e-4,
+                "{whole:?} != {tick:?}"
+            );
+            assert_eq!(whole.speed, tick.speed);
+        }
+    }
+
+    #[test]
+    fn duration_of_nested_patterns() {
+        let duration = |ron| pattern(ron).duration();
+
+        assert_eq!(duration("Ring(count: 1, speed: 1.0)"), Some(0.0));
+        assert_eq!(
+            duration("Spiral(arms: 1, rate: 1.0, turn: 0.0, speed: 1.0)"),
+            None
+        );
+        assert_eq!(
+            duration(
+                "Sequence([
+                    Wait(1.0),
+                    Every(interval: 0.5, times: 3, pattern: Sequence([Ring(count: 1, speed: 1.0), Wait(0.25)])),
+                ])"
+            ),
+            Some(2.25)
+        );
+        assert_eq!(
+            duration(
+                "Parallel([
+                    Wait(1.0),
+                    Rotate(angle: 90.0, pattern: For(duration: 2.0, pattern: Spiral(arms: 1, rate: 1.0, turn: 0.0, speed: 1.0))),
+                ])"
+            ),
+            Some(2.0)
+        );
+        assert_eq!(
+            duration("Parallel([Wait(1.0), Spiral(arms: 1, rate: 1.0, turn: 0.0, speed: 1.0)])"),
+            None
+        );
+        assert_eq!(
+            duration("Every(interval: 1.0, pattern: Ring(count: 1, speed: 1.0))"),
+            None
+        );
+        assert_eq!(
+            duration("Every(interval: 1.0, times: 0, pattern: Spiral(arms: 1, rate: 1.0, turn: 0.0, speed: 1.0))"),
+            Some(0.0)
+        );
+    }
+}
@@ -0,0 +1,448 @@
+use bevy::{
+    ecs::system::{EntityCommands, SystemParam},
+    prelude::*,
+    utils::HashSet,
+};
+
+use crate::bullet_pattern::{BulletFired, BulletPatternSet, Speed};
+
+/// Spawns [`Bullet`]s fired by [`crate::bullet_pattern::BulletEmitter`]s, moves them, and
+/// despawns them once they're too old or leave the [`BulletArena`], all in `FixedUpdate`.
+///
+/// Spawn and despawn bullets through [`Bullets`], which recycles despawned bullet entities
+/// instead of making new ones, and sends a [`BulletDespawned`] for every bullet that goes.
+///
+/// Needs nothing but [`MinimalPlugins`], so it runs headless.
+pub struct BulletsPlugin;
+
+impl Plugin for BulletsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BulletArena>()
+            .init_resource::<BulletPool>()
+            .add_event::<BulletDespawned>()
+            .add_event::<BulletFired>()
+            .configure_sets(
+                FixedUpdate,
+                (
+                    BulletSet::Spawn,
+                    BulletSet::Steer,
+                    BulletSet::Move,
+                    BulletSet::Despawn,
+                )
+                    .chain(),
+            )
+            .add_systems(
+                FixedUpdate,
+                (
+                    spawn_fired_bullets
+                        .in_set(BulletSet::Spawn)
+                        .after(BulletPatternSet),
+                    move_bullets.in_set(BulletSet::Move),
+                    despawn_expired_bullets.in_set(BulletSet::Despawn),
+                ),
+            );
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, SystemSet)]
+pub enum BulletSet {
+    Spawn,
+    /// Behaviors change where bullets go, see [`crate::bullet_behaviors`]
+    Steer,
+    Move,
+    Despawn,
+}
+
+/// A bullet flying through the arena, see [`ArenaPlane`]
+#[derive(Debug, Clone, Component)]
+pub struct Bullet {
+    pub position: Vec2,
+
+    /// Length 1
+    pub direction: Vec2,
+
+    pub speed: Speed,
+
+    /// Seconds since it was fired
+    pub age: f32,
+}
+
+/// Where bullets may be
+#[derive(Debug, Clone, Resource)]
+pub struct BulletArena {
+    pub plane: ArenaPlane,
+    pub bounds: ArenaBounds,
+
+    /// Seconds a bullet lives at most, even if it stays in bounds
+    pub max_lifetime: f32,
+}
+
+impl Default for BulletArena {
+    fn default() -> Self {
+        Self {
+            plane: ArenaPlane::XZ,
+            bounds: ArenaBounds::Circle {
+                center: Vec2::ZERO,
+                radius: 3.5,
+            },
+            max_lifetime: 20.0,
+        }
+    }
+}
+
+/// Which world plane bullets fly in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArenaPlane {
+    /// The ground in 3D, arena X and Y are world X and Z
+    XZ,
+
+    /// The screen in 2D
+    XY,
+}
+
+impl ArenaPlane {
+    /// Drops the coordinate along the plane's normal
+    pub fn project(self, world: Vec3) -> Vec2 {
+        match self {
+            ArenaPlane::XZ => world.xz(),
+            ArenaPlane::XY => world.xy(),
+        }
+    }
+
+    /// Back into the world, `height` along the plane's normal
+    pub fn unproject(self, position: Vec2, height: f32) -> Vec3 {
+        match self {
+            ArenaPlane::XZ => Vec3::new(position.x, height, position.y),
+            ArenaPlane::XY => position.extend(height),
+        }
+    }
+
+    /// The coordinate along the plane's normal
+    pub fn height(self, world: Vec3) -> f32 {
+        match self {
+            ArenaPlane::XZ => world.y,
+            ArenaPlane::XY => world.z,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum ArenaBounds {
+    Circle { center: Vec2, radius: f32 },
+    Rect(Rect),
+}
+
+impl ArenaBounds {
+    pub fn contains(&self, position: Vec2) -> bool {
+        match self {
+            ArenaBounds::Circle { center, radius } => {
+                position.distance_squared(*center) <= radius * radius
+            }
+            ArenaBounds::Rect(rect) => rect.contains(position),
+        }
+    }
+
+    /// The closest position at least `margin` inside
+    pub fn clamp(&self, position: Vec2, margin: f32) -> Vec2 {
+        match self {
+            ArenaBounds::Circle { center, radius } => {
+                *center + (position - *center).clamp_length_max((radius - margin).max(0.0))
+            }
+            ArenaBounds::Rect(rect) => {
+                let inner = rect.inset(-margin);
+                position.clamp(inner.min, inner.max.max(inner.min))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DespawnReason {
+    /// Lived longer than [`BulletArena::max_lifetime`]
+    Expired,
+
+    /// Left [`BulletArena::bounds`]
+    OutOfBounds,
+
+    /// Hit something
+    Hit,
+
+    /// Cleared away, e.g. on restart
+    Cleared,
+
+    /// Split into other bullets, see [`crate::bullet_behaviors::Split`]
+    Split,
+}
+
+/// A bullet is gone, e.g. to spawn sparks where it was
+#[derive(Debug, Clone, Copy, Event)]
+pub struct BulletDespawned {
+    pub entity: Entity,
+    pub position: Vec2,
+    pub reason: DespawnReason,
+}
+
+/// Stays on despawned bullets, which keep nothing else until they're reused
+#[derive(Debug, Clone, Copy, Component)]
+pub struct PooledBullet;
+
+/// Despawned bullet entities, waiting to be reused
+#[derive(Debug, Default, Resource)]
+pub struct BulletPool {
+    /// Reused last in, first out
+    free: Vec<Entity>,
+
+    /// The same as [`Self::free`], to not release a bullet twice in one tick
+    released: HashSet<Entity>,
+
+    /// Entities ever made for bullets
+    allocated: usize,
+}
+
+impl BulletPool {
+    /// How many despawned bullets are waiting to be reused
+    pub fn free(&self) -> usize {
+        self.free.len()
+    }
+
+    /// How many entities were ever made for bullets, alive or waiting in the pool
+    pub fn allocated(&self) -> usize {
+        self.allocated
+    }
+}
+
+/// Spawns and despawns bullets, reusing despawned ones
+#[derive(SystemParam)]
+pub struct Bullets<'w, 's> {
+    commands: Commands<'w, 's>,
+    pool: ResMut<'w, BulletPool>,
+    despawned: EventWriter<'w, BulletDespawned>,
+}
+
+impl<'w, 's> Bullets<'w, 's> {
+    /// Anything inserted on the returned entity is removed again when the bullet is despawned
+    pub fn spawn(&mut self, bullet: Bullet) -> EntityCommands<'_> {
+        match self.pool.free.pop() {
+            Some(entity) => {
+                self.pool.released.remove(&entity);
+                let mut entity = self.commands.entity(entity);
+                entity.insert(bullet);
+                entity
+            }
+            None => {
+                self.pool.allocated += 1;
+                self.commands.spawn((bullet, PooledBullet))
+            }
+        }
+    }
+
+    /// Does nothing if it's already despawned
+    pub fn despawn(&mut self, entity: Entity, position: Vec2, reason: DespawnReason) {
+        if !self.pool.released.insert(entity) {
+            return;
+        }
+
+        self.commands.entity(entity).retain::<PooledBullet>();
+        self.pool.free.push(entity);
+        self.despawned.send(BulletDespawned {
+            entity,
+            position,
+            reason,
+        });
+    }
+}
+
+/// Where `entity` is in the world, from its and its parents' [`Transform`]s, as
+/// [`GlobalTransform`] only follows once per frame, not per tick.
+///
+/// Parented to something animated, this follows the animation, which isn't deterministic.
+fn world_translation(
+    entity: Entity,
+    transforms: &Query<(&Transform, Option<&Parent>)>,
+) -> Option<Vec3> {
+    let (transform, mut parent) = transforms.get(entity).ok()?;
+    let mut world = *transform;
+
+    while let Some(Ok((transform, grandparent))) = parent.map(|parent| transforms.get(parent.get()))
+    {
+        world = transform.mul_transform(world);
+        parent = grandparent;
+    }
+
+    Some(world.translation)
+}
+
+/// Fired bullets start from where their emitter is on the plane
+fn spawn_fired_bullets(
+    arena: Res<BulletArena>,
+    mut fired: EventReader<BulletFired>,
+    transforms: Query<(&Transform, Option<&Parent>)>,
+    mut bullets: Bullets,
+) {
+    for fired in fired.read() {
+        let Some(emitter) = world_translation(fired.emitter, &transforms) else {
+            continue;
+        };
+
+        let origin = arena.plane.project(emitter);
+        fired.behaviors.insert(&mut bullets.spawn(Bullet {
+            position: origin + fired.direction * fired.speed.at(0.0) * fired.age,
+            direction: fired.direction,
+            speed: fired.speed,
+            age: fired.age,
+        }));
+    }
+}
+
+fn move_bullets(time: Res<Time>, mut bullets: Query<&mut Bullet>) {
+    let dt = time.delta_seconds();
+
+    for mut bullet in &mut bullets {
+        let speed = bullet.speed.at(bullet.age);
+        let direction = bullet.direction;
+        bullet.position += direction * speed * dt;
+        bullet.age += dt;
+    }
+}
+
+fn despawn_expired_bullets(
+    arena: Res<BulletArena>,
+    mut bullets: Bullets,
+    query: Query<(Entity, &Bullet)>,
+) {
+    for (entity, bullet) in &query {
+        let reason = if bullet.age > arena.max_lifetime {
+            DespawnReason::Expired
+        } else if !arena.bounds.contains(bullet.position) {
+            DespawnReason::OutOfBounds
+        } else {
+            continue;
+        };
+
+        bullets.despawn(entity, bullet.position, reason);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{f32::consts::TAU, time::Duration};
+
+    use bevy::time::TimeUpdateStrategy;
+
+    use super::*;
+
+    const TICK: Duration = Duration::from_millis(5);
+
+    /// Fire for this many ticks, then wait for the last bullets to go
+    const FIRE_TICKS: u32 = 400;
+    const TICKS: u32 = FIRE_TICKS + 200;
+
+    /// Bullets per tick, every other one standing still until it expires
+    const RING: u32 = 16;
+
+    #[derive(Debug, Default, Resource)]
+    struct Counts {
+        ticks: u32,
+        fired: usize,
+        expired: usize,
+        out_of_bounds: usize,
+    }
+
+    /// Fires rings of bullets into a small arena, then waits for them to go
+    fn run_rings() -> App {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, BulletsPlugin))
+            .insert_resource(Time::<Fixed>::from_duration(TICK))
+            .insert_resource(TimeUpdateStrategy::ManualDuration(TICK))
+            .insert_resource(BulletArena {
+                plane: ArenaPlane::XY,
+                bounds: ArenaBounds::Circle {
+                    center: Vec2::ZERO,
+                    radius: 1.0,
+                },
+                max_lifetime: 0.5,
+            })
+            .init_resource::<Counts>()
+            .add_systems(
+                FixedUpdate,
+                (
+                    fire_rings.in_set(BulletSet::Spawn),
+                    count_despawned.after(BulletSet::Despawn),
+                ),
+            );
+
+        // The first update only starts the clock
+        for _ in 0..=TICKS {
+            app.update();
+        }
+
+        assert_eq!(app.world.resource::<Counts>().ticks, TICKS);
+        app
+    }
+
+    fn fire_rings(mut counts: ResMut<Counts>, mut bullets: Bullets) {
+        counts.ticks += 1;
+        if counts.ticks > FIRE_TICKS {
+            return;
+        }
+
+        for i in 0..RING {
+            let speed = if i % 2 == 0 { 10.0 } else { 0.0 };
+            bullets.spawn(Bullet {
+                position: Vec2::ZERO,
+                direction: Vec2::from_angle(i as f32 / RING as f32 * TAU),
+                speed: Speed::Constant(speed),
+                age: 0.0,
+            });
+            counts.fired += 1;
+        }
+    }
+
+    fn count_despawned(mut counts: ResMut<Counts>, mut despawned: EventReader<BulletDespawned>) {
+        for despawned in despawned.read() {
+            match despawned.reason {
+                DespawnReason::Expired => counts.expired += 1,
+                DespawnReason::OutOfBounds => counts.out_of_bounds += 1,
+                DespawnReason::Hit | DespawnReason::Cleared | DespawnReason::Split => {}
+            }
+        }
+    }
+
+    #[test]
+    fn bullets_leave_the_arena_or_expire() {
+        let mut app = run_rings();
+        let world = &mut app.world;
+
+        assert_eq!(world.query::<&Bullet>().iter(world).count(), 0);
+
+        let counts = world.resource::<Counts>();
+        assert_eq!(counts.fired, (FIRE_TICKS * RING) as usize);
+        assert_eq!(counts.expired, counts.fired / 2, "standing bullets expire");
+        assert_eq!(
+            counts.out_of_bounds,
+            counts.fired / 2,
+            "moving bullets leave"
+        );
+    }
+
+    #[test]
+    fn bullet_entities_are_reused() {
+        let mut app = run_rings();
+        let world = &mut app.world;
+
+        let entities = world
+            .query_filtered::<Entity, With<PooledBullet>>()
+            .iter(world)
+            .count();
+        let fired = world.resource::<Counts>().fired;
+        let pool = world.resource::<BulletPool>();
+
+        assert_eq!(entities, pool.allocated());
+        assert_eq!(pool.free(), entities);
+        assert!(
+            pool.allocated() < fired / 4,
+            "{} allocated",
+            pool.allocated()
+        );
+    }
+}
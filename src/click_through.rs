@@ -0,0 +1,179 @@
+use bevy::{prelude::*, window::RawHandleWrapper};
+
+#[cfg(target_os = "linux")]
+use crate::x11::x11_window;
+use crate::x11::X11Connection;
+
+/// Lets mouse input through windows with a [`ClickThrough`] to whatever is behind them.
+///
+/// On X11, winit's `hit_test` does nothing, so the window gets an XShape input region instead:
+/// Empty, or just the [`ClickThrough::interactive`] regions.
+/// Elsewhere it falls back to `hit_test`, which can't keep regions interactive.
+///
+/// Added by [`crate::default_plugin_extensions::WindowPreset::click_through`], and fine to add again.
+pub struct ClickThroughPlugin;
+
+impl Plugin for ClickThroughPlugin {
+    fn build(&self, app: &mut App) {
+        if app.is_plugin_added::<Self>() {
+            return;
+        }
+
+        X11Connection::init(app);
+        app.add_systems(PostUpdate, (apply_click_through, restore_input));
+    }
+
+    fn is_unique(&self) -> bool {
+        false
+    }
+}
+
+/// Put on a window to let clicks through it
+#[derive(Debug, Clone, Default, PartialEq, Component)]
+pub struct ClickThrough {
+    /// Still receive input here, in logical window coordinates from the top left like
+    /// [`Window::cursor_position`]
+    pub interactive: Vec<Rect>,
+}
+
+/// `None` if this isn't an X11 window, and the caller should fall back to hit testing
+#[cfg(target_os = "linux")]
+fn set_input_region(
+    connection: &X11Connection,
+    handle: &RawHandleWrapper,
+    interactive: Option<(&[Rect], f32)>,
+) -> Option<x11::X11Result<()>> {
+    let connection = connection.0.as_ref()?;
+    let window = x11_window(handle)?;
+
+    Some(match interactive {
+        Some((interactive, scale_factor)) => {
+            x11::set_input_region(connection, window, interactive, scale_factor)
+        }
+        None => x11::reset_input_region(connection, window),
+    })
+}
+
+#[cfg(not(target_os = "linux"))]
+fn set_input_region(
+    _connection: &X11Connection,
+    _handle: &RawHandleWrapper,
+    _interactive: Option<(&[Rect], f32)>,
+) -> Option<Result<(), Box<dyn std::error::Error>>> {
+    None
+}
+
+type ClickThroughChanged = Or<(Changed<ClickThrough>, Changed<RawHandleWrapper>)>;
+
+fn apply_click_through(
+    connection: Res<X11Connection>,
+    mut windows: Query<(&mut Window, &ClickThrough, &RawHandleWrapper), ClickThroughChanged>,
+) {
+    for (mut window, click_through, handle) in &mut windows {
+        let interactive = (click_through.interactive.as_slice(), window.scale_factor());
+
+        match set_input_region(&connection, handle, Some(interactive)) {
+            Some(Ok(())) => {}
+            Some(Err(e)) => error!("Could not make window click-through: {e}"),
+            None => {
+                if !click_through.interactive.is_empty() {
+                    warn!("Interactive click-through regions are only supported on X11");
+                }
+                window.cursor.hit_test = false;
+            }
+        }
+    }
+}
+
+/// Take input everywhere again once [`ClickThrough`] is removed
+fn restore_input(
+    connection: Res<X11Connection>,
+    mut removed: RemovedComponents<ClickThrough>,
+    mut windows: Query<(&mut Window, &RawHandleWrapper)>,
+) {
+    for entity in removed.read() {
+        let Ok((mut window, handle)) = windows.get_mut(entity) else {
+            continue;
+        };
+
+        match set_input_region(&connection, handle, None) {
+            Some(Ok(())) => {}
+            Some(Err(e)) => error!("Could not restore window input: {e}"),
+            None => window.cursor.hit_test = true,
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub mod x11 {
+    use bevy::prelude::*;
+    use x11rb::{
+        protocol::{
+            shape::{ConnectionExt as _, SK, SO},
+            xproto::{ClipOrdering, Rectangle},
+        },
+        rust_connection::RustConnection,
+        NONE,
+    };
+
+    pub type X11Result<T> = Result<T, Box<dyn std::error::Error>>;
+
+    /// Only the given regions (logical pixels) receive input, none if empty
+    pub fn set_input_region(
+        connection: &RustConnection,
+        window: u32,
+        interactive: &[Rect],
+        scale_factor: f32,
+    ) -> X11Result<()> {
+        let rectangles: Vec<_> = interactive
+            .iter()
+            .map(|rect| {
+                let min = (rect.min * scale_factor).round();
+                let size = (rect.size() * scale_factor).round().max(Vec2::ZERO);
+                Rectangle {
+                    x: min.x as i16,
+                    y: min.y as i16,
+                    width: size.x as u16,
+                    height: size.y as u16,
+                }
+            })
+            .collect();
+
+        connection
+            .shape_rectangles(
+                SO::SET,
+                SK::INPUT,
+                ClipOrdering::UNSORTED,
+                window,
+                0,
+                0,
+                &rectangles,
+            )?
+            .check()?;
+        Ok(())
+    }
+
+    /// Back to the whole window receiving input
+    pub fn reset_input_region(connection: &RustConnection, window: u32) -> X11Result<()> {
+        connection
+            .shape_mask(SO::SET, SK::INPUT, window, 0, 0, NONE)?
+            .check()?;
+        Ok(())
+    }
+
+    /// The regions of the window receiving input, in physical pixels
+    pub fn input_region(connection: &RustConnection, window: u32) -> X11Result<Vec<IRect>> {
+        let reply = connection
+            .shape_get_rectangles(window, SK::INPUT)?
+            .reply()?;
+
+        Ok(reply
+            .rectangles
+            .iter()
+            .map(|rect| {
+                let min = IVec2::new(rect.x.into(), rect.y.into());
+                IRect::from_corners(min, min + IVec2::new(rect.width.into(), rect.height.into()))
+            })
+            .collect())
+    }
+}
@@ -1,56 +1,203 @@
 use bevy::{
     app::PluginGroupBuilder,
     prelude::*,
-    window::{Cursor, WindowResolution},
+    window::{PrimaryWindow, WindowLevel, WindowMode},
 };
 
-pub trait DefaultPluginExtensions {
-    /// A decoration-less transparent window always on top at a given position
-    fn with_transparent_window_at(self, pos: IVec2) -> PluginGroupBuilder
-    where
-        Self: Sized,
-    {
-        DefaultPlugins.set(WindowPlugin {
-            primary_window: Some(Window {
-                transparent: true,
-                decorations: false,
-                window_level: bevy::window::WindowLevel::AlwaysOnTop,
-                // TODO: Would be better expressed in a normalized size
-                position: WindowPosition::At(pos),
-                ..default()
-            }),
-            ..default()
-        })
-    }
-
-    /// Sets window plugin to be:
-    /// - Hit test false (but doesn't work on X11)
-    /// - Transparent
-    /// - No decorations
-    /// - Always on top
-    /// - Borderless fullscreen
-    /// - No scale factor (helps make physical resolution match mouse position coords)
-    fn with_transparent_fullscreen_window(self) -> PluginGroupBuilder
-    where
-        Self: Sized,
-    {
-        DefaultPlugins.set(WindowPlugin {
-            primary_window: Some(Window {
-                cursor: Cursor {
-                    // BUG: Doesn't work on X11, seems to be a bug in winit
-                    hit_test: false,
-                    ..default()
-                },
-                transparent: true,
-                decorations: false,
-                window_level: bevy::window::WindowLevel::AlwaysOnTop,
-                mode: bevy::window::WindowMode::BorderlessFullscreen,
-                resolution: WindowResolution::default().with_scale_factor_override(1.0),
-                ..default()
-            }),
-            ..default()
-        })
+use crate::{
+    click_through::{ClickThrough, ClickThroughPlugin},
+    overlay_placement::{OverlayAnchor, OverlayMonitor, OverlayPlacement, OverlayPlacementPlugin},
+    window_handles::{WindowHandles, WindowHandlesPlugin},
+};
+
+pub trait DefaultPluginExtensions: PluginGroup + Sized {
+    /// Applies a [`WindowPreset`] to the primary window.
+    ///
+    /// Only the preset's settings are changed, anything else set on the window or the group
+    /// (e.g. via `.set(WindowPlugin { .. })` before this) is kept.
+    /// Calling it again replaces the earlier preset.
+    fn with_primary_window(self, preset: WindowPreset) -> PluginGroupBuilder {
+        self.build()
+            .add_after::<WindowPlugin, _>(PrimaryWindowPresetPlugin(preset))
+    }
+}
+
+impl<T: PluginGroup> DefaultPluginExtensions for T {}
+
+/// Chainable window settings, for the primary window via
+/// [`DefaultPluginExtensions::with_primary_window`] or extra windows via [`WindowPreset::spawn`].
+///
+/// Setting a monitor, anchor, margin or size places the window with an [`OverlayPlacement`].
+/// Click-through is done with a [`ClickThrough`].
+#[derive(Debug, Clone, Default)]
+pub struct WindowPreset {
+    transparent: bool,
+    click_through: bool,
+    always_on_top: bool,
+    undecorated: bool,
+    borderless_fullscreen: bool,
+    scale_factor_override: Option<f32>,
+    placement: Option<OverlayPlacement>,
+    handles: Option<WindowHandles>,
+}
+
+impl WindowPreset {
+    pub fn new() -> Self {
+        default()
+    }
+
+    /// Transparent, undecorated and always on top
+    pub fn overlay() -> Self {
+        Self::new().transparent().undecorated().always_on_top()
+    }
+
+    /// An overlay covering the whole monitor, letting clicks through.
+    /// No scale factor, which helps make physical resolution match mouse position coords.
+    pub fn transparent_fullscreen() -> Self {
+        Self::overlay()
+            .click_through()
+            .borderless_fullscreen()
+            .scale_factor_override(1.0)
+    }
+
+    /// Remember to also clear to [`Color::NONE`]
+    pub fn transparent(mut self) -> Self {
+        self.transparent = true;
+        self
+    }
+
+    /// Clicks go to whatever is behind the window.
+    /// Change the window's [`ClickThrough`] to keep parts of it interactive.
+    pub fn click_through(mut self) -> Self {
+        self.click_through = true;
+        self
+    }
+
+    pub fn always_on_top(mut self) -> Self {
+        self.always_on_top = true;
+        self
+    }
+
+    pub fn undecorated(mut self) -> Self {
+        self.undecorated = true;
+        self
+    }
+
+    pub fn borderless_fullscreen(mut self) -> Self {
+        self.borderless_fullscreen = true;
+        self
+    }
+
+    pub fn scale_factor_override(mut self, scale_factor: f32) -> Self {
+        self.scale_factor_override = Some(scale_factor);
+        self
+    }
+
+    /// Move and resize with the mouse, see [`WindowHandlesPlugin`]
+    pub fn draggable(mut self) -> Self {
+        self.handles = Some(default());
+        self
+    }
+
+    pub fn monitor(mut self, monitor: OverlayMonitor) -> Self {
+        self.placement_mut().monitor = monitor;
+        self
+    }
+
+    pub fn anchor(mut self, anchor: OverlayAnchor) -> Self {
+        self.placement_mut().anchor = anchor;
+        self
+    }
+
+    /// See [`OverlayPlacement::margin`]
+    pub fn margin(mut self, margin: f32) -> Self {
+        self.placement_mut().margin = margin;
+        self
+    }
+
+    /// See [`OverlayPlacement::size`]
+    pub fn size(mut self, size: Vec2) -> Self {
+        self.placement_mut().size = size;
+        self
+    }
+
+    fn placement_mut(&mut self) -> &mut OverlayPlacement {
+        self.placement.get_or_insert_with(default)
+    }
+
+    /// Changes only what this preset sets, except for placement, click-through and handles which
+    /// are components of their own
+    pub fn apply(&self, window: &mut Window) {
+        if self.transparent {
+            window.transparent = true;
+        }
+        if self.always_on_top {
+            window.window_level = WindowLevel::AlwaysOnTop;
+        }
+        if self.undecorated {
+            window.decorations = false;
+        }
+        if self.borderless_fullscreen {
+            window.mode = WindowMode::BorderlessFullscreen;
+        }
+        if let Some(scale_factor) = self.scale_factor_override {
+            window
+                .resolution
+                .set_scale_factor_override(Some(scale_factor));
+        }
+    }
+
+    pub fn placement(&self) -> Option<&OverlayPlacement> {
+        self.placement.as_ref()
+    }
+
+    /// Spawns an extra window. If it has a placement, is click-through or draggable,
+    /// [`OverlayPlacementPlugin`], [`ClickThroughPlugin`] or [`WindowHandlesPlugin`] must be added.
+    pub fn spawn(&self, commands: &mut Commands, mut window: Window) -> Entity {
+        self.apply(&mut window);
+
+        let mut entity = commands.spawn(window);
+        if let Some(placement) = self.placement.clone() {
+            entity.insert(placement);
+        }
+        if self.click_through {
+            entity.insert(ClickThrough::default());
+        }
+        if let Some(handles) = self.handles.clone() {
+            entity.insert(handles);
+        }
+        entity.id()
     }
 }
 
-impl DefaultPluginExtensions for DefaultPlugins {}
+/// Runs right after [`WindowPlugin`] has spawned the primary window, before it's created by winit
+struct PrimaryWindowPresetPlugin(WindowPreset);
+
+impl Plugin for PrimaryWindowPresetPlugin {
+    fn build(&self, app: &mut App) {
+        let mut primary_window = app
+            .world
+            .query_filtered::<(Entity, &mut Window), With<PrimaryWindow>>();
+
+        let Ok((entity, mut window)) = primary_window.get_single_mut(&mut app.world) else {
+            warn!("No primary window to apply {:?} to", self.0);
+            return;
+        };
+        self.0.apply(&mut window);
+
+        if let Some(placement) = self.0.placement.clone() {
+            app.world.entity_mut(entity).insert(placement);
+            app.add_plugins(OverlayPlacementPlugin);
+        }
+
+        if self.0.click_through {
+            app.world.entity_mut(entity).insert(ClickThrough::default());
+            app.add_plugins(ClickThroughPlugin);
+        }
+
+        if let Some(handles) = self.0.handles.clone() {
+            app.world.entity_mut(entity).insert(handles);
+            app.add_plugins(WindowHandlesPlugin);
+        }
+    }
+}
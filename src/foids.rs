@@ -0,0 +1,311 @@
+use std::f32::consts::TAU;
+
+use bevy::{prelude::*, utils::HashMap};
+
+/// Foids: It's boids, but foxes.
+///
+/// Spawns a flock of animated foxes that steer by separation, alignment and cohesion.
+/// Neighbors are found via a spatial hash, so a few hundred foxes stay interactive.
+///
+/// Tweak [`FoidsParams`] live (e.g. via an inspector) to change the flock's behavior.
+/// Spawn entities with [`FoidObstacle`] to have the flock steer around them.
+pub struct FoidsPlugin {
+    /// How many foxes to spawn
+    pub count: usize,
+}
+
+impl Plugin for FoidsPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<FoidsParams>()
+            .init_resource::<FoidsParams>()
+            .init_resource::<SpatialHash>()
+            .insert_resource(FoidCount(self.count))
+            .add_systems(Startup, spawn_foids)
+            .add_systems(
+                Update,
+                (
+                    start_animations,
+                    (rebuild_spatial_hash, steer, move_foids).chain(),
+                ),
+            );
+    }
+}
+
+/// Live tweakable flocking parameters
+#[derive(Debug, Clone, Resource, Reflect)]
+#[reflect(Resource)]
+pub struct FoidsParams {
+    /// Weight of steering away from close neighbors
+    pub separation_weight: f32,
+    /// Weight of steering towards the average heading of neighbors
+    pub alignment_weight: f32,
+    /// Weight of steering towards the center of neighbors
+    pub cohesion_weight: f32,
+
+    /// Neighbors within this distance affect alignment and cohesion
+    pub neighbor_radius: f32,
+    /// Neighbors within this distance are pushed away from
+    pub separation_radius: f32,
+
+    pub min_speed: f32,
+    pub max_speed: f32,
+    /// Max change in velocity per second
+    pub max_steering: f32,
+
+    /// Foxes are kept within this box centered at the origin
+    pub bounds_half_extents: Vec3,
+    /// Weight of steering back inside the bounds
+    pub bounds_weight: f32,
+
+    /// How far ahead (in seconds of travel) to look for obstacles
+    pub obstacle_lookahead: f32,
+    /// Weight of steering away from obstacles
+    pub obstacle_weight: f32,
+
+    /// Scale applied to the fox model, which is quite large by default
+    pub fox_scale: f32,
+}
+
+impl Default for FoidsParams {
+    fn default() -> Self {
+        Self {
+            separation_weight: 1.5,
+            alignment_weight: 1.0,
+            cohesion_weight: 1.0,
+            neighbor_radius: 2.5,
+            separation_radius: 1.0,
+            min_speed: 2.0,
+            max_speed: 5.0,
+            max_steering: 8.0,
+            bounds_half_extents: Vec3::new(20.0, 0.0, 20.0),
+            bounds_weight: 4.0,
+            obstacle_lookahead: 1.0,
+            obstacle_weight: 6.0,
+            fox_scale: 0.01,
+        }
+    }
+}
+
+/// A fox in the flock
+#[derive(Debug, Clone, Copy, Component)]
+pub struct Foid {
+    pub velocity: Vec3,
+}
+
+/// A sphere the flock steers around
+#[derive(Debug, Clone, Copy, Component)]
+pub struct FoidObstacle {
+    pub radius: f32,
+}
+
+/// Fraction of the run animation this fox starts at, so they don't run in lockstep
+#[derive(Debug, Clone, Copy, Component)]
+struct AnimationPhase(f32);
+
+#[derive(Debug, Resource, Deref)]
+struct FoidCount(usize);
+
+#[derive(Resource)]
+struct FoidAnimation(Handle<AnimationClip>);
+
+/// Buckets foid positions and velocities by grid cell.
+/// The cell size matches the neighbor radius, so only the 27 surrounding cells need checking.
+#[derive(Debug, Default, Resource)]
+struct SpatialHash {
+    cell_size: f32,
+    cells: HashMap<IVec3, Vec<(Entity, Vec3, Vec3)>>,
+}
+
+impl SpatialHash {
+    fn cell(&self, position: Vec3) -> IVec3 {
+        (position / self.cell_size).floor().as_ivec3()
+    }
+
+    fn neighbors(&self, position: Vec3) -> impl Iterator<Item = &(Entity, Vec3, Vec3)> {
+        let center = self.cell(position);
+
+        (-1..=1)
+            .flat_map(|x| (-1..=1).flat_map(move |y| (-1..=1).map(move |z| IVec3::new(x, y, z))))
+            .filter_map(move |offset| self.cells.get(&(center + offset)))
+            .flatten()
+    }
+}
+
+fn spawn_foids(
+    mut commands: Commands,
+    count: Res<FoidCount>,
+    params: Res<FoidsParams>,
+    asset_server: Res<AssetServer>,
+) {
+    let scene = asset_server.load("models/animated/Fox.glb#Scene0");
+    let animation = asset_server.load("models/animated/Fox.glb#Animation2"); // Running!
+    commands.insert_resource(FoidAnimation(animation));
+
+    let extents = params.bounds_half_extents;
+
+    for i in 0..**count {
+        // Golden ratio based scatter, deterministic but well spread
+        let t = i as f32 * 0.618_034;
+        let angle = t * TAU;
+        let radius = (i as f32 / **count as f32).sqrt();
+
+        let translation = Vec3::new(
+            angle.cos() * radius * extents.x,
+            0.0,
+            angle.sin() * radius * extents.z,
+        );
+        let velocity = Vec3::new(-angle.sin(), 0.0, angle.cos()) * params.min_speed;
+
+        commands.spawn((
+            SceneBundle {
+                scene: scene.clone(),
+                transform: Transform::from_translation(translation)
+                    .with_scale(Vec3::splat(params.fox_scale)),
+                ..default()
+            },
+            Foid { velocity },
+            AnimationPhase(t.fract()),
+        ));
+    }
+}
+
+/// Start the run animation once each fox scene has loaded, offset by its phase
+fn start_animations(
+    animation: Res<FoidAnimation>,
+    clips: Res<Assets<AnimationClip>>,
+    mut players: Query<(Entity, &mut AnimationPlayer), Added<AnimationPlayer>>,
+    parents: Query<&Parent>,
+    phases: Query<&AnimationPhase>,
+) {
+    let duration = clips.get(&animation.0).map_or(1.0, |clip| clip.duration());
+
+    for (entity, mut player) in &mut players {
+        let phase = parents
+            .iter_ancestors(entity)
+            .find_map(|ancestor| phases.get(ancestor).ok())
+            .map_or(0.0, |phase| phase.0);
+
+        player
+            .play(animation.0.clone_weak())
+            .repeat()
+            .seek_to(phase * duration);
+    }
+}
+
+fn rebuild_spatial_hash(
+    params: Res<FoidsParams>,
+    mut hash: ResMut<SpatialHash>,
+    foids: Query<(Entity, &Transform, &Foid)>,
+) {
+    hash.cell_size = params.neighbor_radius.max(0.1);
+
+    // Keep allocations around between frames
+    hash.cells.values_mut().for_each(Vec::clear);
+
+    for (entity, transform, foid) in &foids {
+        let cell = hash.cell(transform.translation);
+        hash.cells
+            .entry(cell)
+            .or_default()
+            .push((entity, transform.translation, foid.velocity));
+    }
+}
+
+fn steer(
+    time: Res<Time>,
+    params: Res<FoidsParams>,
+    hash: Res<SpatialHash>,
+    mut foids: Query<(Entity, &Transform, &mut Foid)>,
+    obstacles: Query<(&GlobalTransform, &FoidObstacle)>,
+) {
+    let dt = time.delta_seconds();
+
+    foids
+        .par_iter_mut()
+        .for_each(|(entity, transform, mut foid)| {
+            let position = transform.translation;
+
+            let mut separation = Vec3::ZERO;
+            let mut heading_sum = Vec3::ZERO;
+            let mut center_sum = Vec3::ZERO;
+            let mut num_neighbors = 0;
+
+            for &(other, other_position, other_velocity) in hash.neighbors(position) {
+                if other == entity {
+                    continue;
+                }
+
+                let offset = position - other_position;
+                let distance = offset.length();
+                if distance > params.neighbor_radius {
+                    continue;
+                }
+
+                if distance < params.separation_radius && distance > 0.0 {
+                    // Stronger the closer we are
+                    separation += offset / (distance * distance);
+                }
+
+                heading_sum += other_velocity;
+                center_sum += other_position;
+                num_neighbors += 1;
+            }
+
+            let mut steering = separation * params.separation_weight;
+
+            if num_neighbors > 0 {
+                let n = num_neighbors as f32;
+                steering += (heading_sum / n - foid.velocity) * params.alignment_weight;
+                steering += (center_sum / n - position) * params.cohesion_weight;
+            }
+
+            // Push back inside the bounds, harder the further out we are
+            let outside = position.abs() - params.bounds_half_extents;
+            let outside = outside.max(Vec3::ZERO) * -position.signum();
+            steering += outside * params.bounds_weight;
+
+            // Steer sideways away from any obstacle we are about to run into
+            let ahead = foid.velocity * params.obstacle_lookahead;
+            for (obstacle_transform, obstacle) in &obstacles {
+                let to_obstacle = obstacle_transform.translation() - position;
+                let along = to_obstacle.dot(ahead.normalize_or_zero());
+                if along < 0.0 || along > ahead.length() + obstacle.radius {
+                    continue;
+                }
+
+                let closest = position + ahead.normalize_or_zero() * along;
+                let away = closest - obstacle_transform.translation();
+                let clearance = obstacle.radius + params.separation_radius - away.length();
+                if clearance > 0.0 {
+                    steering += away.try_normalize().unwrap_or(Vec3::X)
+                        * clearance
+                        * params.obstacle_weight;
+                }
+            }
+
+            let steering = steering.clamp_length_max(params.max_steering);
+
+            // Foxes run along the ground
+            let velocity = (foid.velocity + steering * dt) * Vec3::new(1.0, 0.0, 1.0);
+            foid.velocity = velocity.clamp_length(params.min_speed, params.max_speed);
+        });
+}
+
+fn move_foids(
+    time: Res<Time>,
+    params: Res<FoidsParams>,
+    mut foids: Query<(&mut Transform, &Foid)>,
+) {
+    for (mut transform, foid) in &mut foids {
+        transform.translation += foid.velocity * time.delta_seconds();
+        transform.scale = Vec3::splat(params.fox_scale);
+
+        // The fox model faces +Z, and looking points -Z at the target
+        if let Some(heading) = foid.velocity.try_normalize() {
+            let target = Transform::default().looking_to(-heading, Vec3::Y).rotation;
+            transform.rotation = transform
+                .rotation
+                .slerp(target, (time.delta_seconds() * 10.0).min(1.0));
+        }
+    }
+}
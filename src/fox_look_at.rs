@@ -0,0 +1,240 @@
+use std::f32::consts::FRAC_PI_4;
+
+use bevy::{
+    animation::animation_player, prelude::*, transform::TransformSystem, window::PrimaryWindow,
+};
+
+use crate::global_cursor::GlobalCursor;
+
+/// Makes foxes turn their head (and optionally body) towards a target.
+///
+/// Add [`LookAt`] to the root of a fox scene.
+/// Add [`LookAtCursorCamera`] to a camera to have every [`LookAt`] target the mouse cursor,
+/// as seen through that camera. With [`crate::global_cursor::GlobalCursorPlugin`] the cursor is
+/// followed outside the window too.
+///
+/// The neck and head bones are rotated after the [`AnimationPlayer`] has sampled the current pose,
+/// so the look-at is layered on top of whatever animation is playing.
+pub struct FoxLookAtPlugin;
+
+impl Plugin for FoxLookAtPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (find_bones, cursor_to_target))
+            .add_systems(
+                PostUpdate,
+                apply_look_at
+                    .after(animation_player)
+                    .before(TransformSystem::TransformPropagate),
+            );
+    }
+}
+
+/// Turn the head of the fox scene this is on towards [`LookAt::target`]
+#[derive(Debug, Clone, Component)]
+pub struct LookAt {
+    /// World position to look at, or `None` to look straight ahead
+    pub target: Option<Vec3>,
+
+    /// Max head rotation left/right in radians
+    pub max_yaw: f32,
+    /// Max head rotation up/down in radians
+    pub max_pitch: f32,
+
+    /// How quickly the head catches up with the target, higher is snappier
+    pub smoothing: f32,
+
+    /// If set, the body turns at this many radians per second when the target is
+    /// further to the side than the head can turn
+    pub body_turn_speed: Option<f32>,
+}
+
+impl Default for LookAt {
+    fn default() -> Self {
+        Self {
+            target: None,
+            max_yaw: FRAC_PI_4 * 1.5,
+            max_pitch: FRAC_PI_4,
+            smoothing: 8.0,
+            body_turn_speed: None,
+        }
+    }
+}
+
+/// Points every [`LookAt`] at the cursor in the primary window, as seen by this camera
+#[derive(Debug, Clone, Default, Component)]
+pub struct LookAtCursorCamera {
+    /// Where this camera's image is shown in the primary window, in logical pixels from the top left.
+    ///
+    /// `None` when the camera renders directly to the window.
+    pub displayed_at: Option<Rect>,
+}
+
+/// The bones of a fox scene the look-at rotates, and how far they are currently rotated
+#[derive(Debug, Component)]
+struct LookAtBones {
+    neck: Entity,
+    head: Entity,
+
+    yaw: f32,
+    pitch: f32,
+}
+
+/// How much of the rotation is done by the neck, the head does the rest
+const NECK_SHARE: f32 = 0.4;
+
+fn find_bones(
+    mut commands: Commands,
+    foxes: Query<Entity, (With<LookAt>, Without<LookAtBones>)>,
+    children: Query<&Children>,
+    names: Query<&Name>,
+) {
+    for fox in &foxes {
+        let find = |bone: &str| {
+            children
+                .iter_descendants(fox)
+                .find(|&entity| names.get(entity).is_ok_and(|name| name.as_str() == bone))
+        };
+
+        // Not there until the scene has been spawned
+        let (Some(neck), Some(head)) = (find("b_Neck_04"), find("b_Head_05")) else {
+            continue;
+        };
+
+        commands.entity(fox).insert(LookAtBones {
+            neck,
+            head,
+            yaw: 0.0,
+            pitch: 0.0,
+        });
+    }
+}
+
+fn cursor_to_target(
+    window: Query<(&Window, Option<&GlobalCursor>), With<PrimaryWindow>>,
+    camera: Query<(&Camera, &GlobalTransform, &LookAtCursorCamera)>,
+    mut foxes: Query<(&mut LookAt, &LookAtBones)>,
+    bones: Query<&GlobalTransform>,
+) {
+    let Ok((camera, camera_transform, cursor_camera)) = camera.get_single() else {
+        return;
+    };
+
+    // The global cursor keeps working when the cursor leaves the window
+    let Some(cursor) = window
+        .get_single()
+        .ok()
+        .and_then(|(window, global)| match global {
+            Some(global) => global.position,
+            None => window.cursor_position(),
+        })
+    else {
+        return;
+    };
+
+    let viewport_position = match cursor_camera.displayed_at {
+        Some(rect) => {
+            let Some(target_size) = camera.logical_viewport_size() else {
+                return;
+            };
+            // May well be outside the image, which is fine: The fox can look off-screen
+            (cursor - rect.min) / rect.size() * target_size
+        }
+        None => cursor,
+    };
+
+    let Some(ray) = camera.viewport_to_world(camera_transform, viewport_position) else {
+        return;
+    };
+
+    for (mut look_at, look_at_bones) in &mut foxes {
+        let Ok(head) = bones.get(look_at_bones.head) else {
+            continue;
+        };
+
+        // The cursor is assumed to be on a plane facing the camera, at the depth of the head
+        let head = head.translation();
+        let Some(distance) = ray.intersect_plane(head, Plane3d::new(camera_transform.back()))
+        else {
+            continue;
+        };
+
+        look_at.target = Some(ray.get_point(distance));
+    }
+}
+
+fn apply_look_at(
+    time: Res<Time>,
+    mut foxes: Query<(&LookAt, &mut LookAtBones, &mut Transform, &GlobalTransform)>,
+    mut bones: Query<(&mut Transform, &Parent), Without<LookAt>>,
+    globals: Query<&GlobalTransform>,
+) {
+    let dt = time.delta_seconds();
+
+    for (look_at, mut look_at_bones, mut fox_transform, fox_global) in &mut foxes {
+        let Ok(head_global) = globals.get(look_at_bones.head) else {
+            continue;
+        };
+
+        // Yaw and pitch of the target as seen from the head, in the fox's own frame.
+        // The fox model faces +Z.
+        let (desired_yaw, desired_pitch) = match look_at.target {
+            Some(target) => {
+                let to_target = fox_global
+                    .affine()
+                    .inverse()
+                    .transform_vector3(target - head_global.translation());
+
+                (
+                    to_target.x.atan2(to_target.z),
+                    to_target.y.atan2(to_target.xz().length()),
+                )
+            }
+            None => (0.0, 0.0),
+        };
+
+        if let Some(turn_speed) = look_at.body_turn_speed {
+            let beyond = desired_yaw.abs() - look_at.max_yaw;
+            if beyond > 0.0 {
+                let turn = (turn_speed * dt).min(beyond) * desired_yaw.signum();
+                fox_transform.rotate_local_y(turn);
+            }
+        }
+
+        let blend = 1.0 - (-look_at.smoothing * dt).exp();
+        let yaw = desired_yaw.clamp(-look_at.max_yaw, look_at.max_yaw);
+        let pitch = desired_pitch.clamp(-look_at.max_pitch, look_at.max_pitch);
+        look_at_bones.yaw += (yaw - look_at_bones.yaw) * blend;
+        look_at_bones.pitch += (pitch - look_at_bones.pitch) * blend;
+
+        // Positive rotation around X tilts +Z downwards, so negate the pitch
+        let (_, fox_rotation, _) = fox_global.to_scale_rotation_translation();
+        let (yaw, pitch) = (look_at_bones.yaw, look_at_bones.pitch);
+        let world_offset = |share: f32| {
+            fox_rotation
+                * Quat::from_rotation_y(yaw * share)
+                * Quat::from_rotation_x(-pitch * share)
+                * fox_rotation.inverse()
+        };
+
+        // The neck's parent isn't touched by us, so its global transform from last frame is good enough.
+        // The head's parent is the neck, so we chain on from what we just did to the neck.
+        let Ok((mut neck, neck_parent)) = bones.get_mut(look_at_bones.neck) else {
+            continue;
+        };
+        let Ok(neck_parent_global) = globals.get(neck_parent.get()) else {
+            continue;
+        };
+        let (_, parent_rotation, _) = neck_parent_global.to_scale_rotation_translation();
+        neck.rotation =
+            parent_rotation.inverse() * world_offset(NECK_SHARE) * parent_rotation * neck.rotation;
+        let neck_rotation = parent_rotation * neck.rotation;
+
+        let Ok((mut head, _)) = bones.get_mut(look_at_bones.head) else {
+            continue;
+        };
+        head.rotation = neck_rotation.inverse()
+            * world_offset(1.0 - NECK_SHARE)
+            * neck_rotation
+            * head.rotation;
+    }
+}
@@ -0,0 +1,210 @@
+use bevy::{
+    input::{ButtonState, InputSystem},
+    prelude::*,
+    render::camera::RenderTarget,
+    utils::HashSet,
+    window::{PrimaryWindow, RawHandleWrapper},
+};
+
+use crate::x11::X11Connection;
+
+/// Tracks the cursor across the whole desktop, not just while it's over one of our windows.
+///
+/// On X11 the pointer is polled from the X server every frame, so it keeps working through
+/// [`crate::click_through::ClickThrough`] and while other applications have focus.
+/// Elsewhere it falls back to what the windows see.
+///
+/// Every window gets a [`GlobalCursor`], changes are sent as [`GlobalCursorMoved`] and
+/// [`GlobalMouseButton`] events, and [`GlobalMouseButtons`] works like [`ButtonInput`].
+pub struct GlobalCursorPlugin;
+
+impl Plugin for GlobalCursorPlugin {
+    fn build(&self, app: &mut App) {
+        X11Connection::init(app);
+
+        app.init_resource::<GlobalMouseButtons>()
+            .add_event::<GlobalCursorMoved>()
+            .add_event::<GlobalMouseButton>()
+            .add_systems(PreUpdate, poll_global_cursor.after(InputSystem));
+    }
+}
+
+/// Where the cursor is relative to the window this is on, kept up to date by [`GlobalCursorPlugin`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Component)]
+pub struct GlobalCursor {
+    /// Logical pixels from the window's top left like [`Window::cursor_position`],
+    /// but also outside the window. `None` if it's on another screen.
+    pub position: Option<Vec2>,
+
+    /// As seen by the lowest order camera rendering to the window,
+    /// see [`Camera::viewport_to_world_2d`]
+    pub world_position: Option<Vec2>,
+}
+
+#[derive(Debug, Clone, Copy, Event)]
+pub struct GlobalCursorMoved {
+    pub window: Entity,
+    pub cursor: GlobalCursor,
+}
+
+#[derive(Debug, Clone, Copy, Event)]
+pub struct GlobalMouseButton {
+    pub button: MouseButton,
+    pub state: ButtonState,
+}
+
+/// Mouse buttons pressed anywhere on the desktop
+#[derive(Debug, Default, Resource, Deref)]
+pub struct GlobalMouseButtons(ButtonInput<MouseButton>);
+
+/// The cursor in physical pixels from each window's top left, and the pressed buttons
+type PolledPointer = (Vec<(Entity, Option<Vec2>)>, HashSet<MouseButton>);
+
+#[cfg(target_os = "linux")]
+fn poll_x11(
+    connection: &X11Connection,
+    windows: &Query<(Entity, &Window, &RawHandleWrapper)>,
+) -> Option<PolledPointer> {
+    use x11rb::protocol::xproto::{ConnectionExt as _, KeyButMask};
+
+    let connection = connection.0.as_ref()?;
+
+    let mut positions = vec![];
+    let mut buttons = HashSet::new();
+
+    for (entity, _, handle) in windows {
+        let window = crate::x11::x11_window(handle)?;
+        let pointer = match connection
+            .query_pointer(window)
+            .map(|cookie| cookie.reply())
+        {
+            Ok(Ok(pointer)) => pointer,
+            Ok(Err(e)) => {
+                warn!("Could not query pointer: {e}");
+                return None;
+            }
+            Err(e) => {
+                warn!("Could not query pointer: {e}");
+                return None;
+            }
+        };
+
+        let position = pointer
+            .same_screen
+            .then(|| Vec2::new(pointer.win_x.into(), pointer.win_y.into()));
+        positions.push((entity, position));
+
+        let mask = u16::from(pointer.mask);
+        for (bit, button) in [
+            (KeyButMask::BUTTON1, MouseButton::Left),
+            (KeyButMask::BUTTON2, MouseButton::Middle),
+            (KeyButMask::BUTTON3, MouseButton::Right),
+        ] {
+            if mask & u16::from(bit) != 0 {
+                buttons.insert(button);
+            }
+        }
+    }
+
+    Some((positions, buttons))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn poll_x11(
+    _connection: &X11Connection,
+    _windows: &Query<(Entity, &Window, &RawHandleWrapper)>,
+) -> Option<PolledPointer> {
+    None
+}
+
+/// Only knows about the cursor while it's over a window
+fn poll_windows(
+    windows: &Query<(Entity, &Window, &RawHandleWrapper)>,
+    mouse: &ButtonInput<MouseButton>,
+) -> PolledPointer {
+    let positions = windows
+        .iter()
+        .map(|(entity, window, _)| (entity, window.physical_cursor_position()))
+        .collect();
+
+    (positions, mouse.get_pressed().copied().collect())
+}
+
+fn poll_global_cursor(
+    mut commands: Commands,
+    connection: Res<X11Connection>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    windows: Query<(Entity, &Window, &RawHandleWrapper)>,
+    mut cursors: Query<&mut GlobalCursor>,
+    primary_window: Query<Entity, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    mut buttons: ResMut<GlobalMouseButtons>,
+    mut moved: EventWriter<GlobalCursorMoved>,
+    mut button_events: EventWriter<GlobalMouseButton>,
+) {
+    let (positions, pressed) =
+        poll_x11(&connection, &windows).unwrap_or_else(|| poll_windows(&windows, &mouse));
+
+    let primary_window = primary_window.get_single().ok();
+
+    for (entity, physical_position) in positions {
+        let Ok((_, window, _)) = windows.get(entity) else {
+            continue;
+        };
+        let position = physical_position.map(|position| position / window.scale_factor());
+
+        let camera = cameras
+            .iter()
+            .filter(|(camera, _)| match &camera.target {
+                RenderTarget::Window(target) => {
+                    target.normalize(primary_window).map(|w| w.entity()) == Some(entity)
+                }
+                _ => false,
+            })
+            .min_by_key(|(camera, _)| camera.order);
+
+        let world_position = position
+            .zip(camera)
+            .and_then(|(position, (camera, transform))| {
+                camera.viewport_to_world_2d(transform, position)
+            });
+
+        let cursor = GlobalCursor {
+            position,
+            world_position,
+        };
+
+        match cursors.get_mut(entity) {
+            Ok(mut existing) => {
+                if existing.set_if_neq(cursor) {
+                    moved.send(GlobalCursorMoved {
+                        window: entity,
+                        cursor,
+                    });
+                }
+            }
+            Err(_) => {
+                commands.entity(entity).insert(cursor);
+            }
+        }
+    }
+
+    let previously_pressed: HashSet<_> = buttons.get_pressed().copied().collect();
+    buttons.0.clear();
+
+    for &button in previously_pressed.difference(&pressed) {
+        buttons.0.release(button);
+        button_events.send(GlobalMouseButton {
+            button,
+            state: ButtonState::Released,
+        });
+    }
+
+    for &button in pressed.difference(&previously_pressed) {
+        buttons.0.press(button);
+        button_events.send(GlobalMouseButton {
+            button,
+            state: ButtonState::Pressed,
+        });
+    }
+}
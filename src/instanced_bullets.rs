@@ -0,0 +1,504 @@
+use bevy::{
+    core_pipeline::core_3d::Transparent3d,
+    ecs::{
+        query::QueryItem,
+        system::{lifetimeless::*, SystemParamItem},
+    },
+    pbr::{
+        MeshPipeline, MeshPipelineKey, RenderMeshInstances, SetMeshBindGroup, SetMeshViewBindGroup,
+    },
+    prelude::*,
+    render::{
+        extract_component::{ExtractComponent, ExtractComponentPlugin},
+        mesh::{GpuBufferInfo, MeshVertexBufferLayout},
+        render_asset::{
+            PrepareAssetError, RenderAsset, RenderAssetPlugin, RenderAssetUsages, RenderAssets,
+        },
+        render_phase::{
+            AddRenderCommand, DrawFunctions, PhaseItem, RenderCommand, RenderCommandResult,
+            RenderPhase, SetItemPipeline, TrackedRenderPass,
+        },
+        render_resource::*,
+        renderer::{RenderDevice, RenderQueue},
+        texture::FallbackImage,
+        view::{ExtractedView, NoFrustumCulling},
+        Render, RenderApp, RenderSet,
+    },
+    utils::HashMap,
+};
+use bytemuck::{Pod, Zeroable};
+
+/// Draws every bullet of a [`BulletInstances`] in one instanced draw call.
+///
+/// All bullets share one mesh and one [`BulletMaterial`] asset, what differs per bullet goes in a
+/// [`BulletInstance`], which is written to a growing instance buffer every frame.
+/// Bullets are blended, but not sorted among each other.
+pub struct InstancedBulletsPlugin;
+
+impl Plugin for InstancedBulletsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<BulletMaterial>().add_plugins((
+            ExtractComponentPlugin::<BulletInstances>::default(),
+            // After images, so the texture is there for the bind group
+            RenderAssetPlugin::<BulletMaterial, Image>::default(),
+        ));
+        app.sub_app_mut(RenderApp)
+            .add_render_command::<Transparent3d, DrawBullets>()
+            .init_resource::<SpecializedMeshPipelines<BulletPipeline>>()
+            .init_resource::<InstanceBuffers>()
+            .add_systems(
+                Render,
+                (
+                    queue_bullets.in_set(RenderSet::QueueMeshes),
+                    prepare_instance_buffers.in_set(RenderSet::PrepareResources),
+                ),
+            );
+    }
+
+    fn finish(&self, app: &mut App) {
+        app.sub_app_mut(RenderApp).init_resource::<BulletPipeline>();
+    }
+}
+
+/// Samples the texture where [`UvMapping`] says, times `tint` and [`BulletInstance::color`],
+/// plus `emissive`
+#[derive(Asset, TypePath, AsBindGroup, Debug, Clone)]
+#[uniform(2, BulletMaterialUniform)]
+pub struct BulletMaterial {
+    #[texture(0)]
+    #[sampler(1)]
+    pub color_texture: Option<Handle<Image>>,
+
+    pub mapping: UvMapping,
+    pub tint: Color,
+    pub emissive: Color,
+}
+
+impl Default for BulletMaterial {
+    fn default() -> Self {
+        Self {
+            color_texture: None,
+            mapping: default(),
+            tint: Color::WHITE,
+            emissive: Color::BLACK,
+        }
+    }
+}
+
+/// Where on the texture a bullet's surface is, before [`BulletInstance::uv`], wrapped into 0..1
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UvMapping {
+    /// World X and Z, times `scale` plus `offset`, so bullets show the texture laid on the ground
+    WorldPlanar { scale: Vec2, offset: Vec2 },
+
+    /// Wrapped around each bullet like a globe
+    Spherical,
+
+    /// Where it is on screen, so bullets are holes through to the texture
+    ScreenSpace,
+
+    /// Each bullet is globe-mapped to one of `columns` by `rows` tiles, picked by
+    /// [`BulletInstance::seed`]
+    RandomTile { columns: u32, rows: u32 },
+}
+
+impl Default for UvMapping {
+    /// The whole texture spans about the 5 units around the origin
+    fn default() -> Self {
+        UvMapping::WorldPlanar {
+            scale: Vec2::splat(0.4),
+            offset: Vec2::splat(2.0),
+        }
+    }
+}
+
+pub use uniform::BulletMaterialUniform;
+
+// `ShaderType`'s derive generates layout checks which newer compilers consider unused
+#[allow(dead_code)]
+mod uniform {
+    use bevy::{prelude::*, render::render_resource::ShaderType};
+
+    /// `bullet_material.wgsl`'s `BulletMaterial`
+    #[derive(Debug, Clone, ShaderType)]
+    pub struct BulletMaterialUniform {
+        /// Which [`super::UvMapping`], in declaration order
+        pub mapping: u32,
+        pub scale: Vec2,
+        pub offset: Vec2,
+        /// Columns and rows
+        pub tiles: Vec2,
+        /// Linear
+        pub tint: Vec4,
+        pub emissive: Vec4,
+    }
+}
+
+/// The bind group is only remade when the material is added or modified
+impl RenderAsset for BulletMaterial {
+    type PreparedAsset = PreparedBindGroup<()>;
+    type Param = (
+        SRes<RenderDevice>,
+        SRes<BulletPipeline>,
+        SRes<RenderAssets<Image>>,
+        SRes<FallbackImage>,
+    );
+
+    fn asset_usage(&self) -> RenderAssetUsages {
+        RenderAssetUsages::default()
+    }
+
+    fn prepare_asset(
+        self,
+        (render_device, bullet_pipeline, images, fallback_image): &mut SystemParamItem<Self::Param>,
+    ) -> Result<Self::PreparedAsset, PrepareAssetError<Self>> {
+        // Otherwise the texture isn't ready yet
+        self.as_bind_group(
+            &bullet_pipeline.material_layout,
+            render_device,
+            images,
+            fallback_image,
+        )
+        .map_err(|_| PrepareAssetError::RetryNextUpdate(self))
+    }
+}
+
+impl From<&BulletMaterial> for BulletMaterialUniform {
+    fn from(material: &BulletMaterial) -> Self {
+        let (mapping, scale, offset, tiles) = match material.mapping {
+            UvMapping::WorldPlanar { scale, offset } => (0, scale, offset, Vec2::ONE),
+            UvMapping::Spherical => (1, Vec2::ONE, Vec2::ZERO, Vec2::ONE),
+            UvMapping::ScreenSpace => (2, Vec2::ONE, Vec2::ZERO, Vec2::ONE),
+            UvMapping::RandomTile { columns, rows } => (
+                3,
+                Vec2::ONE,
+                Vec2::ZERO,
+                UVec2::new(columns, rows).max(UVec2::ONE).as_vec2(),
+            ),
+        };
+
+        Self {
+            mapping,
+            scale,
+            offset,
+            tiles,
+            tint: Vec4::from_array(material.tint.as_linear_rgba_f32()),
+            emissive: Vec4::from_array(material.emissive.as_linear_rgba_f32()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Pod, Zeroable)]
+#[repr(C)]
+pub struct BulletInstance {
+    /// In world space
+    pub position: Vec3,
+    pub scale: f32,
+
+    /// Multiplies the sampled texture, alpha fades the bullet out
+    pub color: [f32; 4],
+
+    /// Offset (xy) and scale (zw) applied to the texture coordinates
+    pub uv: [f32; 4],
+
+    /// The same for the same bullet, e.g. its entity's index, for [`UvMapping::RandomTile`]
+    pub seed: u32,
+}
+
+impl Default for BulletInstance {
+    fn default() -> Self {
+        Self {
+            position: Vec3::ZERO,
+            scale: 1.0,
+            color: [1.0; 4],
+            uv: [0.0, 0.0, 1.0, 1.0],
+            seed: 0,
+        }
+    }
+}
+
+/// Spawn with the shared mesh, see [`BulletInstancesBundle`], and refill every frame
+#[derive(Debug, Clone, Component)]
+pub struct BulletInstances {
+    pub material: Handle<BulletMaterial>,
+    pub instances: Vec<BulletInstance>,
+}
+
+impl ExtractComponent for BulletInstances {
+    type QueryData = &'static BulletInstances;
+    type QueryFilter = ();
+    type Out = Self;
+
+    fn extract_component(item: QueryItem<'_, Self::QueryData>) -> Option<Self> {
+        Some(item.clone())
+    }
+}
+
+#[derive(Bundle)]
+pub struct BulletInstancesBundle {
+    pub instances: BulletInstances,
+
+    /// Every bullet is drawn with this, scaled by [`BulletInstance::scale`]
+    pub mesh: Handle<Mesh>,
+
+    pub spatial: SpatialBundle,
+
+    /// The bullets are all over the place, wherever the mesh itself is
+    pub no_frustum_culling: NoFrustumCulling,
+}
+
+impl BulletInstancesBundle {
+    pub fn new(mesh: Handle<Mesh>, material: Handle<BulletMaterial>) -> Self {
+        Self {
+            instances: BulletInstances {
+                material,
+                instances: vec![],
+            },
+            mesh,
+            spatial: SpatialBundle::INHERITED_IDENTITY,
+            no_frustum_culling: NoFrustumCulling,
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn queue_bullets(
+    transparent_3d_draw_functions: Res<DrawFunctions<Transparent3d>>,
+    bullet_pipeline: Res<BulletPipeline>,
+    msaa: Res<Msaa>,
+    mut pipelines: ResMut<SpecializedMeshPipelines<BulletPipeline>>,
+    pipeline_cache: Res<PipelineCache>,
+    meshes: Res<RenderAssets<Mesh>>,
+    render_mesh_instances: Res<RenderMeshInstances>,
+    bullets: Query<(Entity, &BulletInstances)>,
+    mut views: Query<(&ExtractedView, &mut RenderPhase<Transparent3d>)>,
+) {
+    let draw_bullets = transparent_3d_draw_functions.read().id::<DrawBullets>();
+
+    let msaa_key = MeshPipelineKey::from_msaa_samples(msaa.samples());
+
+    for (view, mut transparent_phase) in &mut views {
+        let view_key =
+            msaa_key | MeshPipelineKey::from_hdr(view.hdr) | MeshPipelineKey::BLEND_ALPHA;
+        let rangefinder = view.rangefinder3d();
+
+        for (entity, bullets) in &bullets {
+            if bullets.instances.is_empty() {
+                continue;
+            }
+            let Some(mesh_instance) = render_mesh_instances.get(&entity) else {
+                continue;
+            };
+            let Some(mesh) = meshes.get(mesh_instance.mesh_asset_id) else {
+                continue;
+            };
+
+            let key = view_key | MeshPipelineKey::from_primitive_topology(mesh.primitive_topology);
+            let pipeline =
+                match pipelines.specialize(&pipeline_cache, &bullet_pipeline, key, &mesh.layout) {
+                    Ok(pipeline) => pipeline,
+                    Err(e) => {
+                        error!("Could not specialize bullet pipeline: {e}");
+                        continue;
+                    }
+                };
+
+            transparent_phase.add(Transparent3d {
+                entity,
+                pipeline,
+                draw_function: draw_bullets,
+                distance: rangefinder
+                    .distance_translation(&mesh_instance.transforms.transform.translation),
+                batch_range: 0..1,
+                dynamic_offset: None,
+            });
+        }
+    }
+}
+
+/// Per [`BulletInstances`] entity, kept across frames so buffers are only reallocated to grow
+#[derive(Default, Resource)]
+struct InstanceBuffers(HashMap<Entity, BufferVec<BulletInstance>>);
+
+fn prepare_instance_buffers(
+    bullets: Query<(Entity, &BulletInstances)>,
+    mut buffers: ResMut<InstanceBuffers>,
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+) {
+    buffers.0.retain(|&entity, _| bullets.contains(entity));
+
+    for (entity, bullets) in &bullets {
+        let buffer = buffers.0.entry(entity).or_insert_with(|| {
+            let mut buffer = BufferVec::new(BufferUsages::VERTEX);
+            buffer.set_label(Some("bullet instance buffer"));
+            buffer
+        });
+
+        buffer.clear();
+        buffer.extend(bullets.instances.iter().copied());
+        // With room to spare, so a few more bullets don't need a new buffer
+        buffer.reserve(buffer.len().next_power_of_two(), &render_device);
+        buffer.write_buffer(&render_device, &render_queue);
+    }
+}
+
+/// In the render world, for drawing [`BulletInstances`] and preparing [`BulletMaterial`]s
+#[derive(Resource)]
+pub struct BulletPipeline {
+    shader: Handle<Shader>,
+    mesh_pipeline: MeshPipeline,
+    material_layout: BindGroupLayout,
+}
+
+impl FromWorld for BulletPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let asset_server = world.resource::<AssetServer>();
+        let shader = asset_server.load("shaders/bullet_material.wgsl");
+
+        let render_device = world.resource::<RenderDevice>();
+        let material_layout = BulletMaterial::bind_group_layout(render_device);
+
+        let mesh_pipeline = world.resource::<MeshPipeline>();
+
+        BulletPipeline {
+            shader,
+            mesh_pipeline: mesh_pipeline.clone(),
+            material_layout,
+        }
+    }
+}
+
+impl SpecializedMeshPipeline for BulletPipeline {
+    type Key = MeshPipelineKey;
+
+    fn specialize(
+        &self,
+        key: Self::Key,
+        layout: &MeshVertexBufferLayout,
+    ) -> Result<RenderPipelineDescriptor, SpecializedMeshPipelineError> {
+        let mut descriptor = self.mesh_pipeline.specialize(key, layout)?;
+
+        descriptor.vertex.shader = self.shader.clone();
+        // Past anything the mesh itself might use
+        descriptor.vertex.buffers.push(VertexBufferLayout {
+            array_stride: std::mem::size_of::<BulletInstance>() as u64,
+            step_mode: VertexStepMode::Instance,
+            attributes: vec![
+                VertexAttribute {
+                    format: VertexFormat::Float32x4,
+                    offset: 0,
+                    shader_location: 8,
+                },
+                VertexAttribute {
+                    format: VertexFormat::Float32x4,
+                    offset: VertexFormat::Float32x4.size(),
+                    shader_location: 9,
+                },
+                VertexAttribute {
+                    format: VertexFormat::Float32x4,
+                    offset: VertexFormat::Float32x4.size() * 2,
+                    shader_location: 10,
+                },
+                VertexAttribute {
+                    format: VertexFormat::Uint32,
+                    offset: VertexFormat::Float32x4.size() * 3,
+                    shader_location: 11,
+                },
+            ],
+        });
+        descriptor.layout.push(self.material_layout.clone());
+        if let Some(fragment) = descriptor.fragment.as_mut() {
+            fragment.shader = self.shader.clone();
+        }
+        Ok(descriptor)
+    }
+}
+
+type DrawBullets = (
+    SetItemPipeline,
+    SetMeshViewBindGroup<0>,
+    SetMeshBindGroup<1>,
+    SetMaterialBindGroup<2>,
+    DrawMeshInstanced,
+);
+
+struct SetMaterialBindGroup<const I: usize>;
+
+impl<P: PhaseItem, const I: usize> RenderCommand<P> for SetMaterialBindGroup<I> {
+    type Param = SRes<RenderAssets<BulletMaterial>>;
+    type ViewQuery = ();
+    type ItemQuery = Read<BulletInstances>;
+
+    #[inline]
+    fn render<'w>(
+        _item: &P,
+        _view: (),
+        bullets: Option<&'w BulletInstances>,
+        materials: SystemParamItem<'w, '_, Self::Param>,
+        pass: &mut TrackedRenderPass<'w>,
+    ) -> RenderCommandResult {
+        let Some(bullets) = bullets else {
+            return RenderCommandResult::Failure;
+        };
+        let Some(material) = materials.into_inner().get(&bullets.material) else {
+            return RenderCommandResult::Failure;
+        };
+
+        pass.set_bind_group(I, &material.bind_group, &[]);
+        RenderCommandResult::Success
+    }
+}
+
+struct DrawMeshInstanced;
+
+impl<P: PhaseItem> RenderCommand<P> for DrawMeshInstanced {
+    type Param = (
+        SRes<RenderAssets<Mesh>>,
+        SRes<RenderMeshInstances>,
+        SRes<InstanceBuffers>,
+    );
+    type ViewQuery = ();
+    type ItemQuery = ();
+
+    #[inline]
+    fn render<'w>(
+        item: &P,
+        _view: (),
+        _item_query: Option<()>,
+        (meshes, render_mesh_instances, instance_buffers): SystemParamItem<'w, '_, Self::Param>,
+        pass: &mut TrackedRenderPass<'w>,
+    ) -> RenderCommandResult {
+        let Some(mesh_instance) = render_mesh_instances.get(&item.entity()) else {
+            return RenderCommandResult::Failure;
+        };
+        let Some(gpu_mesh) = meshes.into_inner().get(mesh_instance.mesh_asset_id) else {
+            return RenderCommandResult::Failure;
+        };
+        let Some(instance_buffer) = instance_buffers.into_inner().0.get(&item.entity()) else {
+            return RenderCommandResult::Failure;
+        };
+        let Some(buffer) = instance_buffer.buffer() else {
+            return RenderCommandResult::Failure;
+        };
+        let instances = instance_buffer.len() as u32;
+
+        pass.set_vertex_buffer(0, gpu_mesh.vertex_buffer.slice(..));
+        pass.set_vertex_buffer(1, buffer.slice(..));
+
+        match &gpu_mesh.buffer_info {
+            GpuBufferInfo::Indexed {
+                buffer,
+                index_format,
+                count,
+            } => {
+                pass.set_index_buffer(buffer.slice(..), 0, *index_format);
+                pass.draw_indexed(0..*count, 0, 0..instances);
+            }
+            GpuBufferInfo::NonIndexed => {
+                pass.draw(0..gpu_mesh.vertex_count, 0..instances);
+            }
+        }
+        RenderCommandResult::Success
+    }
+}
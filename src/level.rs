@@ -0,0 +1,441 @@
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    prelude::*,
+    utils::{BoxedFuture, HashSet},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    boss::{ArenaPath, BossSet, FollowPath},
+    bullet_game::{BulletGameEvent, GameSet},
+    bullet_pattern::{BulletEmitter, BulletPattern},
+    bullets::{Bullet, BulletArena, Bullets, DespawnReason},
+    simulation::SimulationAssets,
+};
+
+/// Plays [`Level`]s, timelines of waves, with [`LevelRunner`]s, in [`LevelSet`].
+///
+/// Levels are RON files ending in `.level.ron`, see `assets/levels/` for examples. Each wave
+/// spawns its emitters as children of the runner when their time comes, and despawns them when
+/// it's over. Background, music and camera cues are sent as [`LevelEvent`]s, for the app to
+/// present.
+///
+/// For debugging, N skips to the next wave, B restarts the current one and L loops it, see
+/// [`LevelInput`]. Restarting the game restarts waves too, and so does saving the level while the
+/// app runs, unless [`LevelSettings::restart_on_reload`] is off.
+pub struct LevelPlugin;
+
+impl Plugin for LevelPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<Level>()
+            .register_asset_loader(LevelLoader)
+            .add_event::<BulletGameEvent>()
+            .add_event::<LevelEvent>()
+            .init_resource::<LevelInput>()
+            .init_resource::<LevelSettings>()
+            .init_resource::<ReloadedLevels>()
+            .init_resource::<SimulationAssets>()
+            .configure_sets(FixedUpdate, LevelSet.after(GameSet::Player).before(BossSet))
+            .add_systems(PreUpdate, preload_levels)
+            .add_systems(Update, note_reloaded_levels)
+            .add_systems(
+                FixedUpdate,
+                (
+                    read_level_input.in_set(GameSet::Input),
+                    (restart_reloaded_levels, restart_levels, run_levels)
+                        .chain()
+                        .in_set(LevelSet),
+                ),
+            );
+    }
+}
+
+/// Waves start and end here, before emitters move and fire
+#[derive(Debug, Clone, PartialEq, Eq, Hash, SystemSet)]
+pub struct LevelSet;
+
+/// Waves, one after the other, loaded from a `.level.ron` file
+#[derive(Debug, Clone, Asset, TypePath, Deserialize)]
+pub struct Level {
+    pub waves: Vec<Wave>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Wave {
+    #[serde(default)]
+    pub name: String,
+
+    /// Seconds until the next wave
+    pub duration: f32,
+
+    pub emitters: Vec<WaveEmitter>,
+
+    /// Clear all bullets when it starts
+    #[serde(default)]
+    pub clear: bool,
+
+    /// Cues, which keep what the previous wave had if `None`
+    #[serde(default)]
+    pub background: Option<Color>,
+    /// Relative to `assets/`, looped
+    #[serde(default)]
+    pub music: Option<String>,
+    #[serde(default)]
+    pub camera: Option<CameraMove>,
+}
+
+/// An emitter during part of a wave
+#[derive(Debug, Clone, Deserialize)]
+pub struct WaveEmitter {
+    /// Relative to `assets/`
+    pub pattern: String,
+    /// [`Self::pattern`], loaded with the level
+    #[serde(skip)]
+    pub handle: Handle<BulletPattern>,
+
+    /// Seconds into the wave
+    #[serde(default)]
+    pub start: f32,
+    /// Seconds it fires for, until the wave ends if `None`
+    #[serde(default)]
+    pub duration: Option<f32>,
+
+    /// On the arena plane, from the runner
+    #[serde(default)]
+    pub position: Vec2,
+    /// Replaces `position`
+    #[serde(default)]
+    pub path: Option<ArenaPath>,
+}
+
+impl WaveEmitter {
+    fn active(&self, wave_time: f32) -> bool {
+        wave_time >= self.start
+            && self
+                .duration
+                .is_none_or(|duration| wave_time < self.start + duration)
+    }
+}
+
+/// Where the camera goes when a wave starts
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct CameraMove {
+    pub position: Vec3,
+    #[serde(default)]
+    pub look_at: Vec3,
+    /// How long it takes to get there
+    #[serde(default)]
+    pub seconds: f32,
+}
+
+/// Plays a [`Level`], spawn one with a [`Transform`], e.g. with [`LevelRunner::new`]
+#[derive(Debug, Clone, Component)]
+pub struct LevelRunner {
+    pub level: Handle<Level>,
+
+    /// Index into [`Level::waves`], their length once finished
+    pub wave: usize,
+    /// Seconds since the current wave started
+    pub wave_time: f32,
+
+    /// Repeat the current wave instead of going on
+    pub loop_wave: bool,
+
+    /// Counts wave (re)starts, so emitters of an earlier start are replaced
+    generation: u32,
+    /// The generation [`LevelEvent`]s were sent for
+    announced: Option<u32>,
+    /// Debug controls act on press, not while held
+    held: LevelInput,
+}
+
+impl LevelRunner {
+    pub fn new(level: Handle<Level>) -> Self {
+        Self {
+            level,
+            wave: 0,
+            wave_time: 0.0,
+            loop_wave: false,
+            generation: 0,
+            announced: None,
+            held: default(),
+        }
+    }
+
+    /// The wave being played, if the level is loaded and not finished
+    pub fn current<'a>(&self, levels: &'a Assets<Level>) -> Option<&'a Wave> {
+        levels.get(&self.level)?.waves.get(self.wave)
+    }
+
+    pub fn finished(&self, levels: &Assets<Level>) -> bool {
+        levels
+            .get(&self.level)
+            .is_some_and(|level| self.wave >= level.waves.len())
+    }
+
+    fn start_wave(&mut self, wave: usize) {
+        self.wave = wave;
+        self.wave_time = 0.0;
+        self.generation += 1;
+    }
+}
+
+/// Debug controls for [`LevelRunner`]s this tick, recorded in replays like the player's input
+#[derive(Debug, Clone, Copy, Default, PartialEq, Resource, Serialize, Deserialize)]
+pub struct LevelInput {
+    pub skip: bool,
+    pub restart: bool,
+    pub loop_wave: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Event)]
+pub enum LevelEvent {
+    WaveStarted { runner: Entity, wave: usize },
+    Finished { runner: Entity },
+}
+
+#[derive(Debug, Clone, Resource)]
+pub struct LevelSettings {
+    /// Restart waves of levels saved while the app runs. Turned off by
+    /// [`crate::replay::ReplayPlugin`], as a replay can't know when that happened.
+    pub restart_on_reload: bool,
+}
+
+impl Default for LevelSettings {
+    fn default() -> Self {
+        Self {
+            restart_on_reload: true,
+        }
+    }
+}
+
+/// Levels modified since the last tick, whose runners restart their wave in [`LevelSet`]
+#[derive(Debug, Default, Resource)]
+struct ReloadedLevels(HashSet<AssetId<Level>>);
+
+/// Marks an emitter spawned for a wave
+#[derive(Debug, Clone, Copy, Component)]
+struct LevelEmitter {
+    generation: u32,
+    /// Index into [`Wave::emitters`]
+    index: usize,
+}
+
+/// Levels and their patterns are loaded before the simulation starts
+fn preload_levels(
+    mut assets: ResMut<SimulationAssets>,
+    runners: Query<&LevelRunner, Added<LevelRunner>>,
+) {
+    for runner in &runners {
+        assets.push(runner.level.clone().untyped());
+    }
+}
+
+/// Asset events come every frame, not every tick, so they're kept for the next one
+fn note_reloaded_levels(
+    settings: Res<LevelSettings>,
+    mut events: EventReader<AssetEvent<Level>>,
+    mut reloaded: ResMut<ReloadedLevels>,
+) {
+    for event in events.read() {
+        let AssetEvent::Modified { id } = event else {
+            continue;
+        };
+
+        if settings.restart_on_reload {
+            info!("Level {id} reloaded, restarting its wave");
+            reloaded.0.insert(*id);
+        } else {
+            warn!("Level {id} reloaded, the run won't play out like it would have");
+        }
+    }
+}
+
+fn restart_reloaded_levels(
+    mut reloaded: ResMut<ReloadedLevels>,
+    mut runners: Query<&mut LevelRunner>,
+) {
+    if reloaded.0.is_empty() {
+        return;
+    }
+
+    for mut runner in &mut runners {
+        if reloaded.0.contains(&runner.level.id()) {
+            let wave = runner.wave;
+            runner.start_wave(wave);
+        }
+    }
+    reloaded.0.clear();
+}
+
+fn read_level_input(keys: Res<ButtonInput<KeyCode>>, mut input: ResMut<LevelInput>) {
+    *input = LevelInput {
+        skip: keys.pressed(KeyCode::KeyN),
+        restart: keys.pressed(KeyCode::KeyB),
+        loop_wave: keys.pressed(KeyCode::KeyL),
+    };
+}
+
+fn restart_levels(mut events: EventReader<BulletGameEvent>, mut runners: Query<&mut LevelRunner>) {
+    if !events
+        .read()
+        .any(|event| *event == BulletGameEvent::Restarted)
+    {
+        return;
+    }
+
+    for mut runner in &mut runners {
+        runner.start_wave(0);
+    }
+}
+
+fn run_levels(
+    mut commands: Commands,
+    time: Res<Time>,
+    arena: Res<BulletArena>,
+    levels: Res<Assets<Level>>,
+    input: Res<LevelInput>,
+    mut runners: Query<(Entity, &mut LevelRunner, Option<&Children>)>,
+    emitters: Query<&LevelEmitter>,
+    existing: Query<(Entity, &Bullet)>,
+    mut bullets: Bullets,
+    mut events: EventWriter<LevelEvent>,
+) {
+    for (entity, mut runner, children) in &mut runners {
+        let Some(level) = levels.get(&runner.level) else {
+            continue;
+        };
+
+        let held = std::mem::replace(&mut runner.held, *input);
+        let mut clear = false;
+        if input.skip && !held.skip && runner.wave < level.waves.len() {
+            let wave = runner.wave + 1;
+            runner.start_wave(wave);
+            clear = true;
+        }
+        if input.restart && !held.restart {
+            let wave = runner.wave.min(level.waves.len().saturating_sub(1));
+            runner.start_wave(wave);
+            clear = true;
+        }
+        if input.loop_wave && !held.loop_wave {
+            runner.loop_wave = !runner.loop_wave;
+            info!("Looping wave: {}", runner.loop_wave);
+        }
+
+        if let Some(wave) = level.waves.get(runner.wave) {
+            if runner.wave_time >= wave.duration {
+                let next = if runner.loop_wave {
+                    runner.wave
+                } else {
+                    runner.wave + 1
+                };
+                runner.start_wave(next);
+            }
+        }
+
+        let wave = level.waves.get(runner.wave);
+        if runner.announced != Some(runner.generation) {
+            runner.announced = Some(runner.generation);
+
+            match wave {
+                Some(wave) => {
+                    info!("Wave {} {}", runner.wave + 1, wave.name);
+                    clear |= wave.clear;
+                    events.send(LevelEvent::WaveStarted {
+                        runner: entity,
+                        wave: runner.wave,
+                    });
+                }
+                None => {
+                    info!("Level finished");
+                    events.send(LevelEvent::Finished { runner: entity });
+                }
+            }
+
+            if clear {
+                for (cleared, bullet) in &existing {
+                    bullets.despawn(cleared, bullet.position, DespawnReason::Cleared);
+                }
+            }
+        }
+
+        // Emitters come and go as the wave goes on
+        let mut present = vec![false; wave.map_or(0, |wave| wave.emitters.len())];
+        for &child in children.into_iter().flatten() {
+            let Ok(emitter) = emitters.get(child) else {
+                continue;
+            };
+
+            // Emitters of an earlier start may be of a wave that has since been reloaded shorter
+            let active = emitter.generation == runner.generation
+                && wave
+                    .and_then(|wave| wave.emitters.get(emitter.index))
+                    .is_some_and(|wave_emitter| wave_emitter.active(runner.wave_time));
+            if active {
+                present[emitter.index] = true;
+            } else {
+                commands.entity(child).despawn_recursive();
+            }
+        }
+
+        if let Some(wave) = wave {
+            commands.entity(entity).with_children(|parent| {
+                for (index, emitter) in wave.emitters.iter().enumerate() {
+                    if present[index] || !emitter.active(runner.wave_time) {
+                        continue;
+                    }
+
+                    let mut spawned = parent.spawn((
+                        TransformBundle::from_transform(Transform::from_translation(
+                            arena.plane.unproject(emitter.position, 0.0),
+                        )),
+                        BulletEmitter::new(emitter.handle.clone()),
+                        LevelEmitter {
+                            generation: runner.generation,
+                            index,
+                        },
+                    ));
+                    if let Some(path) = &emitter.path {
+                        spawned.insert(FollowPath::new(path.clone()));
+                    }
+                }
+            });
+
+            runner.wave_time += time.delta_seconds();
+        }
+    }
+}
+
+struct LevelLoader;
+
+impl AssetLoader for LevelLoader {
+    type Asset = Level;
+    type Settings = ();
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Level, Self::Error>> {
+        Box::pin(async move {
+            let mut ron = String::new();
+            reader.read_to_string(&mut ron).await?;
+            let mut level: Level = ron::from_str(&ron)?;
+
+            for wave in &mut level.waves {
+                for emitter in &mut wave.emitters {
+                    emitter.handle = load_context.load(&emitter.pattern);
+                }
+            }
+
+            Ok(level)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["level.ron"]
+    }
+}
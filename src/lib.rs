@@ -1,14 +1,84 @@
 pub mod blender_cam;
+pub mod boss;
+pub mod bullet_behaviors;
+pub mod bullet_benchmark;
+pub mod bullet_game;
+pub mod bullet_pattern;
+pub mod bullets;
+pub mod click_through;
+pub mod window_handles;
+pub mod window_layout;
 pub mod world_axes_gizmo;
 
 pub mod bevy_example_animated_fox;
 
+pub mod foids;
+pub mod fox_look_at;
+pub mod global_cursor;
+
+pub mod instanced_bullets;
+pub mod level;
+pub mod mip_generation;
+pub mod multisampling;
+pub mod offline_render;
+pub mod overlay_placement;
+pub mod png_capture;
+pub mod render_target_pool;
 pub mod render_util;
+pub mod replay;
+pub mod simulation;
+pub mod sprite_bullets;
+mod x11;
 
 pub mod prelude {
     pub use super::{
         bevy_example_animated_fox::BevyExampleAnimatedFoxPlugin,
-        default_plugin_extensions::DefaultPluginExtensions, world_axes_gizmo::WorldAxesGizmoPlugin,
+        boss::{
+            ArenaPath, Boss, BossEvent, BossPhase, BossPlugin, BossSet, FollowPath, PhaseEmitter,
+            PhaseEnd,
+        },
+        bullet_behaviors::{
+            Accelerate, AimAtPlayer, BulletBehaviors, BulletBehaviorsPlugin, Curve, Homing, Split,
+        },
+        bullet_benchmark::{BenchmarkStep, BulletBenchmarkPlugin},
+        bullet_game::{
+            ArenaCamera, BulletGame, BulletGameEvent, BulletGameParams, BulletGamePlugin,
+            GamePhase, GameSet, Player, PlayerInput,
+        },
+        bullet_pattern::{
+            BulletEmitter, BulletFired, BulletPattern, BulletPatternPlugin, BulletPatternSet, Speed,
+        },
+        bullets::{
+            ArenaBounds, ArenaPlane, Bullet, BulletArena, BulletDespawned, BulletSet, Bullets,
+            BulletsPlugin, DespawnReason,
+        },
+        click_through::{ClickThrough, ClickThroughPlugin},
+        default_plugin_extensions::{DefaultPluginExtensions, WindowPreset},
+        foids::{FoidObstacle, FoidsParams, FoidsPlugin},
+        fox_look_at::{FoxLookAtPlugin, LookAt, LookAtCursorCamera},
+        global_cursor::{
+            GlobalCursor, GlobalCursorMoved, GlobalCursorPlugin, GlobalMouseButton,
+            GlobalMouseButtons,
+        },
+        instanced_bullets::InstancedBulletsPlugin,
+        level::{
+            CameraMove, Level, LevelEvent, LevelInput, LevelPlugin, LevelRunner, LevelSet,
+            LevelSettings, Wave, WaveEmitter,
+        },
+        offline_render::OfflineRenderPlugin,
+        overlay_placement::{
+            OverlayAnchor, OverlayMonitor, OverlayPlacement, OverlayPlacementPlugin,
+        },
+        png_capture::{CapturePng, CaptureTarget, PngCapturePlugin, PngCaptureSettings, PngSaved},
+        render_target_pool::{PooledRenderTarget, RenderTargetPool, RenderTargetPoolPlugin},
+        replay::{Replay, ReplayPlugin, ReplayResult},
+        simulation::{
+            Simulation, SimulationAssets, SimulationPlugin, SimulationRng, SimulationSet,
+        },
+        sprite_bullets::{BulletSpriteMaterial, BulletSprites, SpriteBulletsPlugin},
+        window_handles::{WindowHandles, WindowHandlesPlugin},
+        window_layout::{PersistWindowLayout, WindowLayoutPlugin},
+        world_axes_gizmo::WorldAxesGizmoPlugin,
     };
 }
 
@@ -0,0 +1,263 @@
+use bevy::{
+    core_pipeline::fullscreen_vertex_shader::fullscreen_shader_vertex_state,
+    prelude::*,
+    render::{
+        extract_resource::{ExtractResource, ExtractResourcePlugin},
+        graph::CameraDriverLabel,
+        render_asset::RenderAssets,
+        render_graph::{Node, NodeRunError, RenderGraph, RenderGraphContext, RenderLabel},
+        render_resource::{
+            binding_types::{sampler, texture_2d},
+            BindGroupEntries, BindGroupLayout, BindGroupLayoutEntries, CachedRenderPipelineId,
+            ColorTargetState, ColorWrites, Extent3d, FragmentState, LoadOp, Operations,
+            PipelineCache, RenderPassColorAttachment, RenderPassDescriptor,
+            RenderPipelineDescriptor, Sampler, SamplerBindingType, SamplerDescriptor, ShaderStages,
+            SpecializedRenderPipeline, SpecializedRenderPipelines, StoreOp, TextureFormat,
+            TextureSampleType, TextureViewDescriptor,
+        },
+        renderer::{RenderContext, RenderDevice},
+        Render, RenderApp, RenderSet,
+    },
+    utils::HashMap,
+};
+
+/// Fills in the mip chains of images made by
+/// [`crate::render_util::RenderTargetBuilder::build_with_mips`].
+///
+/// Runs after all cameras have rendered, so mips are from this frame's render target contents.
+pub struct MipGenerationPlugin;
+
+impl Plugin for MipGenerationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GenerateMips>()
+            .add_plugins(ExtractResourcePlugin::<GenerateMips>::default());
+
+        let Ok(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+
+        render_app
+            .init_resource::<SpecializedRenderPipelines<MipPipeline>>()
+            .init_resource::<MipPipelineIds>()
+            .add_systems(Render, queue_pipelines.in_set(RenderSet::Queue));
+
+        let mut render_graph = render_app.world.resource_mut::<RenderGraph>();
+        render_graph.add_node(MipGenerationLabel, MipGenerationNode);
+        render_graph.add_node_edge(CameraDriverLabel, MipGenerationLabel);
+    }
+
+    fn finish(&self, app: &mut App) {
+        let Ok(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+
+        render_app.init_resource::<MipPipeline>();
+    }
+}
+
+/// Images which get mips generated every frame
+#[derive(Debug, Clone, Default, Resource, ExtractResource)]
+pub struct GenerateMips(Vec<MipChain>);
+
+#[derive(Debug, Clone)]
+struct MipChain {
+    /// Copied into the top mip of [`Self::mipmapped`]
+    source: Handle<Image>,
+    mipmapped: Handle<Image>,
+}
+
+impl GenerateMips {
+    /// Every frame, copy `source` into the top mip of `mipmapped` then fill in the rest of its mips.
+    /// Both must have the same size and format.
+    pub fn add(&mut self, source: Handle<Image>, mipmapped: Handle<Image>) {
+        self.0.push(MipChain { source, mipmapped });
+    }
+
+    /// Stop generating mips for `mipmapped`
+    pub fn remove(&mut self, mipmapped: &Handle<Image>) {
+        self.0
+            .retain(|chain| chain.mipmapped.id() != mipmapped.id());
+    }
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, RenderLabel)]
+struct MipGenerationLabel;
+
+#[derive(Resource)]
+struct MipPipeline {
+    layout: BindGroupLayout,
+    sampler: Sampler,
+    shader: Handle<Shader>,
+}
+
+impl FromWorld for MipPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let render_device = world.resource::<RenderDevice>();
+
+        let layout = render_device.create_bind_group_layout(
+            "mip_generation_bind_group_layout",
+            &BindGroupLayoutEntries::sequential(
+                ShaderStages::FRAGMENT,
+                (
+                    texture_2d(TextureSampleType::Float { filterable: true }),
+                    sampler(SamplerBindingType::Filtering),
+                ),
+            ),
+        );
+
+        // Linear filtering averages the four texels of the bigger mip
+        let sampler = render_device.create_sampler(&SamplerDescriptor {
+            label: Some("mip_generation_sampler"),
+            mag_filter: bevy::render::render_resource::FilterMode::Linear,
+            min_filter: bevy::render::render_resource::FilterMode::Linear,
+            ..default()
+        });
+
+        let shader = world
+            .resource::<AssetServer>()
+            .load("shaders/mip_downsample.wgsl");
+
+        Self {
+            layout,
+            sampler,
+            shader,
+        }
+    }
+}
+
+impl SpecializedRenderPipeline for MipPipeline {
+    type Key = TextureFormat;
+
+    fn specialize(&self, format: Self::Key) -> RenderPipelineDescriptor {
+        RenderPipelineDescriptor {
+            label: Some("mip_generation_pipeline".into()),
+            layout: vec![self.layout.clone()],
+            push_constant_ranges: vec![],
+            vertex: fullscreen_shader_vertex_state(),
+            fragment: Some(FragmentState {
+                shader: self.shader.clone(),
+                shader_defs: vec![],
+                entry_point: "fragment".into(),
+                targets: vec![Some(ColorTargetState {
+                    format,
+                    blend: None,
+                    write_mask: ColorWrites::ALL,
+                })],
+            }),
+            primitive: default(),
+            depth_stencil: None,
+            multisample: default(),
+        }
+    }
+}
+
+/// One pipeline per texture format in use
+#[derive(Debug, Default, Resource, Deref, DerefMut)]
+struct MipPipelineIds(HashMap<TextureFormat, CachedRenderPipelineId>);
+
+fn queue_pipelines(
+    pipeline_cache: Res<PipelineCache>,
+    pipeline: Res<MipPipeline>,
+    mut pipelines: ResMut<SpecializedRenderPipelines<MipPipeline>>,
+    mut ids: ResMut<MipPipelineIds>,
+    generate_mips: Res<GenerateMips>,
+    images: Res<RenderAssets<Image>>,
+) {
+    for chain in &generate_mips.0 {
+        let Some(image) = images.get(&chain.mipmapped) else {
+            continue;
+        };
+
+        ids.entry(image.texture_format).or_insert_with(|| {
+            pipelines.specialize(&pipeline_cache, &pipeline, image.texture_format)
+        });
+    }
+}
+
+struct MipGenerationNode;
+
+impl Node for MipGenerationNode {
+    fn run<'w>(
+        &self,
+        _graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext<'w>,
+        world: &'w World,
+    ) -> Result<(), NodeRunError> {
+        let generate_mips = world.resource::<GenerateMips>();
+        let images = world.resource::<RenderAssets<Image>>();
+        let pipeline = world.resource::<MipPipeline>();
+        let pipeline_cache = world.resource::<PipelineCache>();
+        let ids = world.resource::<MipPipelineIds>();
+
+        let render_device = render_context.render_device().clone();
+
+        for chain in &generate_mips.0 {
+            let (Some(source), Some(mipmapped)) =
+                (images.get(&chain.source), images.get(&chain.mipmapped))
+            else {
+                continue;
+            };
+
+            let Some(render_pipeline) = ids
+                .get(&mipmapped.texture_format)
+                .and_then(|id| pipeline_cache.get_render_pipeline(*id))
+            else {
+                continue;
+            };
+
+            render_context.command_encoder().copy_texture_to_texture(
+                source.texture.as_image_copy(),
+                mipmapped.texture.as_image_copy(),
+                Extent3d {
+                    width: source.size.x as u32,
+                    height: source.size.y as u32,
+                    depth_or_array_layers: 1,
+                },
+            );
+
+            // Each mip is drawn by sampling the one above it
+            for mip in 1..mipmapped.mip_level_count {
+                let mip_view = |level| {
+                    mipmapped.texture.create_view(&TextureViewDescriptor {
+                        label: Some("mip_generation_view"),
+                        base_mip_level: level,
+                        mip_level_count: Some(1),
+                        ..default()
+                    })
+                };
+                let bigger = mip_view(mip - 1);
+                let smaller = mip_view(mip);
+
+                let bind_group = render_device.create_bind_group(
+                    "mip_generation_bind_group",
+                    &pipeline.layout,
+                    &BindGroupEntries::sequential((&bigger, &pipeline.sampler)),
+                );
+
+                let mut pass =
+                    render_context
+                        .command_encoder()
+                        .begin_render_pass(&RenderPassDescriptor {
+                            label: Some("mip_generation_pass"),
+                            color_attachments: &[Some(RenderPassColorAttachment {
+                                view: &smaller,
+                                resolve_target: None,
+                                ops: Operations {
+                                    load: LoadOp::Clear(default()),
+                                    store: StoreOp::Store,
+                                },
+                            })],
+                            depth_stencil_attachment: None,
+                            timestamp_writes: None,
+                            occlusion_query_set: None,
+                        });
+
+                pass.set_pipeline(render_pipeline);
+                pass.set_bind_group(0, &bind_group, &[]);
+                pass.draw(0..3, 0..1);
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,101 @@
+use bevy::{
+    prelude::*,
+    render::{
+        extract_resource::{ExtractResource, ExtractResourcePlugin},
+        render_asset::RenderAssets,
+        render_resource::{Extent3d, TextureDescriptor, TextureDimension, TextureUsages},
+        renderer::RenderDevice,
+        texture::{CachedTexture, TextureCache},
+        Render, RenderApp, RenderSet,
+    },
+    utils::HashMap,
+};
+
+/// Gives images made by [`crate::render_util::RenderTargetBuilder::build_multisampled`]
+/// multisampled textures in the render world, see [`MultisampledTextures`].
+///
+/// Multisampled textures can't be uploaded from the main world, so they only exist here,
+/// and custom passes resolve them into the (single sampled) image.
+pub struct MultisamplingPlugin;
+
+impl Plugin for MultisamplingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MultisampledImages>()
+            .add_plugins(ExtractResourcePlugin::<MultisampledImages>::default());
+
+        let Ok(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+
+        render_app
+            .init_resource::<MultisampledTextures>()
+            .add_systems(
+                Render,
+                prepare_multisampled_textures.in_set(RenderSet::PrepareResources),
+            );
+    }
+}
+
+/// Images which get a multisampled texture in the render world, with their sample counts
+#[derive(Debug, Clone, Default, Resource, ExtractResource)]
+pub struct MultisampledImages(HashMap<AssetId<Image>, u32>);
+
+impl MultisampledImages {
+    /// Give `image` a texture with `sample_count` samples per pixel, of the same size and format
+    pub fn add(&mut self, image: &Handle<Image>, sample_count: u32) {
+        self.0.insert(image.id(), sample_count);
+    }
+
+    /// Stop making a multisampled texture for `image`
+    pub fn remove(&mut self, image: &Handle<Image>) {
+        self.0.remove(&image.id());
+    }
+}
+
+/// This frame's multisampled textures for [`MultisampledImages`], in the render world.
+///
+/// Render into one of these and set the image's texture view as the pass's `resolve_target`.
+/// Images which aren't on the GPU yet don't have one.
+#[derive(Default, Resource)]
+pub struct MultisampledTextures(HashMap<AssetId<Image>, CachedTexture>);
+
+impl MultisampledTextures {
+    pub fn get(&self, image: impl Into<AssetId<Image>>) -> Option<&CachedTexture> {
+        self.0.get(&image.into())
+    }
+}
+
+fn prepare_multisampled_textures(
+    images: Res<MultisampledImages>,
+    gpu_images: Res<RenderAssets<Image>>,
+    render_device: Res<RenderDevice>,
+    mut texture_cache: ResMut<TextureCache>,
+    mut textures: ResMut<MultisampledTextures>,
+) {
+    textures.0.clear();
+
+    for (&id, &sample_count) in &images.0 {
+        let Some(gpu_image) = gpu_images.get(id) else {
+            continue;
+        };
+
+        let texture = texture_cache.get(
+            &render_device,
+            TextureDescriptor {
+                label: Some("multisampled_texture"),
+                size: Extent3d {
+                    width: gpu_image.size.x as u32,
+                    height: gpu_image.size.y as u32,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count,
+                dimension: TextureDimension::D2,
+                format: gpu_image.texture_format,
+                usage: TextureUsages::RENDER_ATTACHMENT,
+                view_formats: &[],
+            },
+        );
+        textures.0.insert(id, texture);
+    }
+}
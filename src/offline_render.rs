@@ -0,0 +1,296 @@
+use std::{path::PathBuf, time::Duration};
+
+use bevy::{
+    app::AppExit,
+    asset::RecursiveDependencyLoadState,
+    prelude::*,
+    render::camera::{CameraUpdateSystem, RenderTarget},
+    time::{TimeSystem, TimeUpdateStrategy},
+    window::{PrimaryWindow, WindowRef},
+};
+
+use crate::{
+    png_capture::{CapturePng, CaptureTarget, PngCaptureFailed, PngCapturePlugin, PngSaved},
+    render_util::RenderTargetBuilder,
+};
+
+/// Renders a fixed number of frames into a numbered PNG sequence, then exits.
+///
+/// Does nothing unless `STREAMVILLE_OFFLINE_FRAMES` is set, so any binary can add it.
+///
+/// Time advances by exactly one frame's worth per captured frame regardless of how long rendering
+/// takes, so anything driven by [`Time`] (including `FixedUpdate`) plays out the same on every run.
+/// It stands still until the scenes, meshes and images in use have loaded, and while each frame
+/// is saved. A frame that couldn't be captured is captured again, up to [`MAX_ATTEMPTS`] times,
+/// so the sequence has no gaps.
+/// The window is hidden, and cameras rendering to it render to an offscreen image instead.
+///
+/// # Environment
+///
+/// - `STREAMVILLE_OFFLINE_FRAMES`: How many frames to render
+/// - `STREAMVILLE_OFFLINE_SIZE`: Resolution, default `1920x1080`
+/// - `STREAMVILLE_OFFLINE_FPS`: Virtual frames per second, default `60`
+/// - `STREAMVILLE_OFFLINE_WARMUP`: Frames to let things settle once assets have loaded, default `60`
+/// - `STREAMVILLE_OFFLINE_DIR`: Where frames end up, default `renders`
+///
+/// In CI, run under Xvfb with a software driver, e.g. Mesa's lavapipe via `WGPU_BACKEND=vulkan`.
+pub struct OfflineRenderPlugin;
+
+impl Plugin for OfflineRenderPlugin {
+    fn build(&self, app: &mut App) {
+        let Some(settings) = OfflineRenderSettings::from_env() else {
+            return;
+        };
+
+        info!("Offline render: {settings:?}");
+
+        if !app.is_plugin_added::<PngCapturePlugin>() {
+            app.add_plugins(PngCapturePlugin);
+        }
+
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(
+            1.0 / settings.fps,
+        )))
+        .insert_resource(settings)
+        .init_resource::<OfflineRenderProgress>()
+        .add_systems(Startup, (hide_window, setup_target))
+        .add_systems(First, (warmup, step_time).chain().before(TimeSystem))
+        .add_systems(Update, capture_frame)
+        .add_systems(
+            PostUpdate,
+            retarget_window_cameras.before(CameraUpdateSystem),
+        )
+        .add_systems(Last, exit_when_done);
+    }
+}
+
+#[derive(Debug, Clone, Resource)]
+pub struct OfflineRenderSettings {
+    pub frames: u32,
+    pub resolution: UVec2,
+    pub fps: f64,
+    pub warmup_frames: u32,
+    pub directory: PathBuf,
+}
+
+impl OfflineRenderSettings {
+    pub fn from_env() -> Option<Self> {
+        let var = |name: &str| std::env::var(format!("STREAMVILLE_OFFLINE_{name}")).ok();
+
+        let frames = var("FRAMES")?.parse().ok()?;
+
+        let resolution = var("SIZE")
+            .and_then(|size| {
+                let (width, height) = size.split_once('x')?;
+                Some(UVec2::new(width.parse().ok()?, height.parse().ok()?))
+            })
+            .unwrap_or(UVec2::new(1920, 1080));
+
+        Some(Self {
+            frames,
+            resolution,
+            fps: var("FPS").and_then(|fps| fps.parse().ok()).unwrap_or(60.0),
+            warmup_frames: var("WARMUP").and_then(|w| w.parse().ok()).unwrap_or(60),
+            directory: var("DIR").map(PathBuf::from).unwrap_or("renders".into()),
+        })
+    }
+}
+
+/// Tries per frame before giving up on the render
+pub const MAX_ATTEMPTS: u32 = 5;
+
+#[derive(Debug, Default, Resource)]
+struct OfflineRenderProgress {
+    /// Frames since everything loaded
+    warmed_up_frames: u32,
+
+    /// The frame to capture next, or being captured
+    frame: u32,
+    /// Where the frame being captured goes, until it's saved or failed
+    waiting: Option<PathBuf>,
+    /// Failed captures of the current frame
+    attempts: u32,
+    /// Let time advance by one frame, once the last one is saved
+    advance: bool,
+
+    finished: u32,
+}
+
+impl OfflineRenderProgress {
+    fn warmed_up(&self, settings: &OfflineRenderSettings) -> bool {
+        self.warmed_up_frames >= settings.warmup_frames
+    }
+}
+
+/// What the window's cameras render to instead
+#[derive(Debug, Resource, Deref)]
+struct OfflineRenderTarget(Handle<Image>);
+
+fn hide_window(settings: Res<OfflineRenderSettings>, mut window: Query<&mut Window>) {
+    for mut window in &mut window {
+        window.visible = false;
+        window
+            .resolution
+            .set_physical_resolution(settings.resolution.x, settings.resolution.y);
+    }
+}
+
+fn setup_target(
+    mut commands: Commands,
+    settings: Res<OfflineRenderSettings>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    let target = RenderTargetBuilder::new(settings.resolution)
+        .label("offline_render_target")
+        .readback()
+        .build(&mut images);
+
+    commands.insert_resource(OfflineRenderTarget(target));
+}
+
+/// Waits for assets in use to load, and for things to settle after, so the first frame is the
+/// same on every run
+fn warmup(
+    settings: Res<OfflineRenderSettings>,
+    asset_server: Res<AssetServer>,
+    scenes: Query<&Handle<Scene>>,
+    meshes: Query<&Handle<Mesh>>,
+    images: Query<&Handle<Image>>,
+    mut progress: ResMut<OfflineRenderProgress>,
+) {
+    if progress.warmed_up(&settings) {
+        return;
+    }
+
+    // Assets made in code rather than loaded have no load state, and are ready
+    let ids = scenes
+        .iter()
+        .map(|handle| handle.id().untyped())
+        .chain(meshes.iter().map(|handle| handle.id().untyped()))
+        .chain(images.iter().map(|handle| handle.id().untyped()));
+    let mut loading = 0;
+    for id in ids {
+        match asset_server.get_recursive_dependency_load_state(id) {
+            Some(
+                RecursiveDependencyLoadState::NotLoaded | RecursiveDependencyLoadState::Loading,
+            ) => loading += 1,
+            Some(RecursiveDependencyLoadState::Failed) if progress.warmed_up_frames == 0 => {
+                warn!("Asset {id:?} failed to load, rendering without it")
+            }
+            _ => {}
+        }
+    }
+
+    // Settling starts over whenever something new is loading
+    if loading > 0 {
+        progress.warmed_up_frames = 0;
+    } else {
+        progress.warmed_up_frames += 1;
+    }
+}
+
+/// Time only moves on once a frame is captured
+fn step_time(mut progress: ResMut<OfflineRenderProgress>, mut time: ResMut<Time<Virtual>>) {
+    if std::mem::take(&mut progress.advance) {
+        time.unpause();
+    } else {
+        time.pause();
+    }
+}
+
+fn retarget_window_cameras(
+    target: Res<OfflineRenderTarget>,
+    primary_window: Query<Entity, With<PrimaryWindow>>,
+    mut cameras: Query<&mut Camera>,
+) {
+    let primary_window = primary_window.get_single().ok();
+
+    for mut camera in &mut cameras {
+        let RenderTarget::Window(window) = camera.target else {
+            continue;
+        };
+
+        let renders_to_primary = match window {
+            WindowRef::Primary => true,
+            WindowRef::Entity(entity) => Some(entity) == primary_window,
+        };
+
+        if renders_to_primary {
+            camera.target = RenderTarget::Image(target.clone());
+        }
+    }
+}
+
+fn capture_frame(
+    settings: Res<OfflineRenderSettings>,
+    target: Res<OfflineRenderTarget>,
+    mut progress: ResMut<OfflineRenderProgress>,
+    mut captures: EventWriter<CapturePng>,
+) {
+    if !progress.warmed_up(&settings)
+        || progress.waiting.is_some()
+        || progress.frame >= settings.frames
+    {
+        return;
+    }
+
+    let path = settings
+        .directory
+        .join(format!("frame-{:05}.png", progress.frame));
+
+    captures.send(CapturePng {
+        path: Some(path.clone()),
+        ..CapturePng::new(CaptureTarget::Image(target.clone()), "frame")
+    });
+    progress.waiting = Some(path);
+}
+
+fn exit_when_done(
+    settings: Res<OfflineRenderSettings>,
+    mut progress: ResMut<OfflineRenderProgress>,
+    mut saved: EventReader<PngSaved>,
+    mut failed: EventReader<PngCaptureFailed>,
+    mut exit: EventWriter<AppExit>,
+) {
+    for saved in saved.read() {
+        if progress.waiting.as_ref() != Some(&saved.path) {
+            continue;
+        }
+
+        progress.waiting = None;
+        progress.attempts = 0;
+        progress.frame += 1;
+        progress.finished += 1;
+        progress.advance = true;
+    }
+
+    for failed in failed.read() {
+        if progress.waiting.as_ref() != Some(&failed.path) {
+            continue;
+        }
+
+        // Time stood still, so trying again captures the same frame
+        progress.waiting = None;
+        progress.attempts += 1;
+        if progress.attempts >= MAX_ATTEMPTS {
+            error!(
+                "Could not capture {} after {MAX_ATTEMPTS} attempts, stopping the offline render at {} of {} frames",
+                failed.path.display(),
+                progress.finished,
+                settings.frames
+            );
+            exit.send(AppExit);
+            return;
+        }
+        warn!("Could not capture {}, trying again", failed.path.display());
+    }
+
+    if progress.finished >= settings.frames {
+        info!(
+            "Offline render of {} frames done, see {}",
+            settings.frames,
+            settings.directory.display()
+        );
+        exit.send(AppExit);
+    }
+}
@@ -0,0 +1,321 @@
+use bevy::{prelude::*, utils::HashSet, winit::WinitWindows};
+
+/// Places windows with an [`OverlayPlacement`] on a monitor, in sizes relative to that monitor.
+///
+/// Placement is resolved again whenever monitors are plugged in, removed or change resolution,
+/// so the same settings work on 1080p and 4K setups.
+///
+/// Added by [`crate::default_plugin_extensions::WindowPreset`]s with a placement,
+/// and fine to add again.
+pub struct OverlayPlacementPlugin;
+
+impl Plugin for OverlayPlacementPlugin {
+    fn build(&self, app: &mut App) {
+        if app.world.contains_resource::<KnownMonitors>() {
+            return;
+        }
+
+        app.init_resource::<KnownMonitors>()
+            .add_systems(Update, place_windows);
+    }
+
+    fn is_unique(&self) -> bool {
+        false
+    }
+}
+
+/// The monitors as of the last placement, to notice when they change
+#[derive(Debug, Default, Resource)]
+struct KnownMonitors(Vec<MonitorRect>);
+
+#[derive(Debug, Clone, Component)]
+pub struct OverlayPlacement {
+    pub monitor: OverlayMonitor,
+
+    /// Which corner, edge or point of the monitor the window sticks to
+    pub anchor: OverlayAnchor,
+
+    /// Space kept between the window and the monitor's edges,
+    /// as a fraction of the monitor's shorter side so it's even on both axes
+    pub margin: f32,
+
+    /// Window size as a fraction of the monitor's width and height
+    pub size: Vec2,
+}
+
+impl Default for OverlayPlacement {
+    fn default() -> Self {
+        Self {
+            monitor: OverlayMonitor::Primary,
+            anchor: OverlayAnchor::TopRight,
+            margin: 0.05,
+            size: Vec2::splat(0.25),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OverlayMonitor {
+    Primary,
+
+    /// In the order the OS lists them, falls back to the primary monitor if out of range
+    Index(usize),
+
+    /// E.g. `DP-1` as shown by `xrandr`, falls back to the primary monitor if not connected
+    Name(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OverlayAnchor {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+
+    /// (0, 0) is the top left and (1, 1) the bottom right of the space within the margins
+    Normalized(Vec2),
+}
+
+impl OverlayAnchor {
+    pub fn normalized(self) -> Vec2 {
+        match self {
+            Self::TopLeft => Vec2::new(0.0, 0.0),
+            Self::Top => Vec2::new(0.5, 0.0),
+            Self::TopRight => Vec2::new(1.0, 0.0),
+            Self::Left => Vec2::new(0.0, 0.5),
+            Self::Center => Vec2::new(0.5, 0.5),
+            Self::Right => Vec2::new(1.0, 0.5),
+            Self::BottomLeft => Vec2::new(0.0, 1.0),
+            Self::Bottom => Vec2::new(0.5, 1.0),
+            Self::BottomRight => Vec2::new(1.0, 1.0),
+            Self::Normalized(normalized) => normalized,
+        }
+    }
+}
+
+/// A monitor in physical pixels on the virtual desktop
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MonitorRect {
+    pub name: Option<String>,
+    pub primary: bool,
+    pub position: IVec2,
+    pub size: UVec2,
+}
+
+impl MonitorRect {
+    pub fn rect(&self) -> IRect {
+        IRect::from_corners(self.position, self.position + self.size.as_ivec2())
+    }
+}
+
+/// All monitors, as seen by a window. `None` until winit has created the window.
+pub fn available_monitors(
+    winit_windows: &WinitWindows,
+    window: Entity,
+) -> Option<Vec<MonitorRect>> {
+    let winit_window = winit_windows.get_window(window)?;
+    let primary = winit_window.primary_monitor();
+
+    let monitors = winit_window
+        .available_monitors()
+        .map(|monitor| MonitorRect {
+            name: monitor.name(),
+            primary: Some(&monitor) == primary.as_ref(),
+            position: IVec2::new(monitor.position().x, monitor.position().y),
+            size: UVec2::new(monitor.size().width, monitor.size().height),
+        })
+        .collect();
+
+    Some(monitors)
+}
+
+impl OverlayPlacement {
+    /// Window position and size in physical pixels
+    pub fn resolve(&self, monitor: &MonitorRect) -> (IVec2, UVec2) {
+        let monitor_size = monitor.size.as_vec2();
+        let margin = Vec2::splat(self.margin * monitor_size.min_element());
+
+        let size = (monitor_size * self.size).max(Vec2::ONE);
+        let free_space = (monitor_size - size - 2.0 * margin).max(Vec2::ZERO);
+        let offset = margin + free_space * self.anchor.normalized().clamp(Vec2::ZERO, Vec2::ONE);
+
+        (
+            monitor.position + offset.round().as_ivec2(),
+            size.round().as_uvec2(),
+        )
+    }
+
+    fn pick_monitor<'a>(&self, monitors: &'a [MonitorRect]) -> Option<&'a MonitorRect> {
+        let picked = match &self.monitor {
+            OverlayMonitor::Primary => None,
+            OverlayMonitor::Index(index) => monitors.get(*index),
+            OverlayMonitor::Name(name) => monitors
+                .iter()
+                .find(|monitor| monitor.name.as_ref() == Some(name)),
+        };
+
+        if picked.is_none() && self.monitor != OverlayMonitor::Primary {
+            warn!(
+                "Monitor {:?} not found, using the primary one. Monitors: {monitors:?}",
+                self.monitor
+            );
+        }
+
+        // Some platforms (e.g. Wayland) don't say which monitor is primary
+        picked
+            .or(monitors.iter().find(|monitor| monitor.primary))
+            .or(monitors.first())
+    }
+}
+
+fn place_windows(
+    winit_windows: NonSend<WinitWindows>,
+    mut windows: Query<(Entity, &mut Window, Ref<OverlayPlacement>)>,
+    mut known_monitors: ResMut<KnownMonitors>,
+    mut placed: Local<HashSet<Entity>>,
+) {
+    // Any window can list the monitors, they're the same for all
+    let Some(monitors) = windows
+        .iter()
+        .find_map(|(entity, ..)| available_monitors(&winit_windows, entity))
+    else {
+        return;
+    };
+
+    if known_monitors.0 != monitors {
+        info!("Monitors changed, placing overlay windows again: {monitors:?}");
+        known_monitors.0 = monitors;
+        placed.clear();
+    }
+
+    for (entity, mut window, placement) in &mut windows {
+        if placement.is_changed() {
+            placed.remove(&entity);
+        }
+
+        // Not created yet
+        if winit_windows.get_window(entity).is_none() || placed.contains(&entity) {
+            continue;
+        }
+
+        let Some(monitor) = placement.pick_monitor(&known_monitors.0) else {
+            continue;
+        };
+
+        let (position, size) = placement.resolve(monitor);
+        debug!("Placing {entity:?} at {position} with size {size} on {monitor:?}");
+
+        window.position = WindowPosition::At(position);
+        window.resolution.set_physical_resolution(size.x, size.y);
+        placed.insert(entity);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn monitor(name: &str, primary: bool, position: IVec2) -> MonitorRect {
+        MonitorRect {
+            name: Some(name.to_owned()),
+            primary,
+            position,
+            size: UVec2::new(1920, 1080),
+        }
+    }
+
+    fn placement(anchor: OverlayAnchor) -> OverlayPlacement {
+        OverlayPlacement {
+            anchor,
+            ..default()
+        }
+    }
+
+    #[test]
+    fn anchors_within_the_margins() {
+        let right = monitor("DP-2", false, IVec2::new(1920, 0));
+        // 5% of 1080 is 54 pixels of margin, around a 480x270 window
+        let size = UVec2::new(480, 270);
+
+        assert_eq!(
+            placement(OverlayAnchor::TopLeft).resolve(&right),
+            (IVec2::new(1920 + 54, 54), size)
+        );
+        assert_eq!(
+            placement(OverlayAnchor::TopRight).resolve(&right),
+            (IVec2::new(1920 + 1920 - 54 - 480, 54), size)
+        );
+        assert_eq!(
+            placement(OverlayAnchor::Center).resolve(&right),
+            (IVec2::new(1920 + 720, 405), size)
+        );
+        assert_eq!(
+            placement(OverlayAnchor::BottomRight).resolve(&right),
+            (IVec2::new(1920 + 1920 - 54 - 480, 1080 - 54 - 270), size)
+        );
+        assert_eq!(
+            placement(OverlayAnchor::Normalized(Vec2::new(0.25, 2.0))).resolve(&right),
+            placement(OverlayAnchor::Normalized(Vec2::new(0.25, 1.0))).resolve(&right),
+        );
+    }
+
+    #[test]
+    fn margins_dont_push_windows_off_the_monitor() {
+        let primary = monitor("DP-1", true, IVec2::ZERO);
+        let placement = OverlayPlacement {
+            anchor: OverlayAnchor::BottomRight,
+            margin: 0.25,
+            size: Vec2::new(0.75, 0.5),
+            ..default()
+        };
+
+        // 270 pixels of margin leave no free space, so the window sits at the top left margin
+        assert_eq!(
+            placement.resolve(&primary),
+            (IVec2::splat(270), UVec2::new(1440, 540))
+        );
+
+        let no_margin = OverlayPlacement {
+            margin: 0.0,
+            size: Vec2::ONE,
+            ..placement
+        };
+        assert_eq!(
+            no_margin.resolve(&primary),
+            (IVec2::ZERO, UVec2::new(1920, 1080))
+        );
+    }
+
+    #[test]
+    fn picks_monitors_falling_back_to_the_primary_one() {
+        let monitors = [
+            monitor("DP-1", false, IVec2::ZERO),
+            monitor("DP-2", true, IVec2::new(1920, 0)),
+        ];
+        let pick = |monitor| {
+            OverlayPlacement {
+                monitor,
+                ..default()
+            }
+            .pick_monitor(&monitors)
+            .and_then(|monitor| monitor.name.clone())
+        };
+
+        assert_eq!(pick(OverlayMonitor::Primary).as_deref(), Some("DP-2"));
+        assert_eq!(pick(OverlayMonitor::Index(0)).as_deref(), Some("DP-1"));
+        assert_eq!(pick(OverlayMonitor::Index(5)).as_deref(), Some("DP-2"));
+        assert_eq!(
+            pick(OverlayMonitor::Name("DP-1".to_owned())).as_deref(),
+            Some("DP-1")
+        );
+        assert_eq!(
+            pick(OverlayMonitor::Name("HDMI-1".to_owned())).as_deref(),
+            Some("DP-2")
+        );
+    }
+}
@@ -0,0 +1,514 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{channel, Receiver, Sender},
+        Mutex,
+    },
+};
+
+use bevy::{
+    prelude::*,
+    render::{
+        render_asset::{RenderAssetUsages, RenderAssets},
+        render_resource::{
+            BufferDescriptor, BufferUsages, CommandEncoderDescriptor, Extent3d, ImageCopyBuffer,
+            ImageDataLayout, MapMode, TextureDimension, TextureFormat,
+        },
+        renderer::{RenderDevice, RenderQueue},
+        texture::TextureFormatPixelInfo,
+        view::screenshot::ScreenshotManager,
+        Extract, ExtractSchedule, Render, RenderApp, RenderSet,
+    },
+    tasks::AsyncComputeTaskPool,
+    window::{PrimaryWindow, WindowRef},
+};
+use image::{DynamicImage, RgbaImage};
+
+/// Saves windows and offscreen images (e.g. [`crate::bevy_example_animated_fox::FoxRenderTarget`])
+/// as PNG files.
+///
+/// Send [`CapturePng`] events, or press the hotkey in [`PngCaptureSettings`].
+/// Reading back from the GPU and encoding is done off the main thread, so frames don't stall.
+/// A [`PngSaved`] event is sent when a file has been written, or [`PngCaptureFailed`] if it couldn't be.
+///
+/// Offscreen images need [`TextureUsages::COPY_SRC`](bevy::render::render_resource::TextureUsages),
+/// see [`crate::render_util::RenderTargetBuilder::readback`]. Linear and float formats are
+/// converted to 8 bit sRGB, HDR values are clamped.
+pub struct PngCapturePlugin;
+
+impl Plugin for PngCapturePlugin {
+    fn build(&self, app: &mut App) {
+        let (saved_sender, saved_receiver) = channel();
+
+        app.init_resource::<PngCaptureSettings>()
+            .add_event::<CapturePng>()
+            .add_event::<PngSaved>()
+            .add_event::<PngCaptureFailed>()
+            .insert_resource(PngCaptureChannels {
+                image_requests: default(),
+                saved: saved_sender.clone(),
+                saved_receiver: Mutex::new(saved_receiver),
+            })
+            // Late, so captures requested during `Update` get this frame's contents
+            .add_systems(
+                PostUpdate,
+                (capture_on_hotkey, start_captures, send_saved_events).chain(),
+            );
+
+        let Ok(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+
+        render_app
+            .insert_resource(ImageCaptureRequests {
+                requests: vec![],
+                saved: saved_sender,
+            })
+            .add_systems(ExtractSchedule, extract_image_requests)
+            // After the render graph has been submitted, so we copy this frame's contents
+            .add_systems(Render, copy_images_to_buffers.in_set(RenderSet::Cleanup));
+    }
+}
+
+/// Rows of texture to buffer copies must be aligned to this, see `wgpu::COPY_BYTES_PER_ROW_ALIGNMENT`
+const COPY_BYTES_PER_ROW_ALIGNMENT: u32 = 256;
+
+#[derive(Debug, Clone, Resource)]
+pub struct PngCaptureSettings {
+    /// Where files end up, created if needed
+    pub directory: PathBuf,
+
+    /// Pressing this captures all of [`Self::hotkey_targets`]
+    pub hotkey: Option<KeyCode>,
+
+    /// What to capture on hotkey, and what to call the files
+    pub hotkey_targets: Vec<(String, CaptureTarget)>,
+}
+
+impl Default for PngCaptureSettings {
+    fn default() -> Self {
+        Self {
+            directory: PathBuf::from("captures"),
+            hotkey: Some(KeyCode::F12),
+            hotkey_targets: vec![(
+                "window".to_owned(),
+                CaptureTarget::Window(WindowRef::Primary),
+            )],
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum CaptureTarget {
+    /// An offscreen image, which must have been created with `COPY_SRC` usage
+    Image(Handle<Image>),
+
+    /// A window's swapchain
+    Window(WindowRef),
+}
+
+/// Save a PNG of a target
+#[derive(Debug, Clone, Event)]
+pub struct CapturePng {
+    pub target: CaptureTarget,
+
+    /// Prefix of the file name, a timestamp is added after it
+    pub name: String,
+
+    /// Write to exactly this path instead, ignoring the name and the capture directory
+    pub path: Option<PathBuf>,
+}
+
+impl CapturePng {
+    pub fn new(target: CaptureTarget, name: impl Into<String>) -> Self {
+        Self {
+            target,
+            name: name.into(),
+            path: None,
+        }
+    }
+}
+
+/// A capture has been written to disk
+#[derive(Debug, Clone, Event)]
+pub struct PngSaved {
+    pub path: PathBuf,
+}
+
+/// A capture could not be read back or written, see the logs for why
+#[derive(Debug, Clone, Event)]
+pub struct PngCaptureFailed {
+    pub path: PathBuf,
+}
+
+#[derive(Resource)]
+struct PngCaptureChannels {
+    /// Taken by the render world during extraction, so requests are served the same frame
+    image_requests: Mutex<Vec<ImageCaptureRequest>>,
+
+    saved: Sender<CaptureResult>,
+    saved_receiver: Mutex<Receiver<CaptureResult>>,
+}
+
+struct CaptureResult {
+    path: PathBuf,
+    saved: bool,
+}
+
+/// Sent to the render world, which has the GPU side of images
+struct ImageCaptureRequest {
+    image: AssetId<Image>,
+    path: PathBuf,
+}
+
+#[derive(Resource)]
+struct ImageCaptureRequests {
+    requests: Vec<ImageCaptureRequest>,
+    saved: Sender<CaptureResult>,
+}
+
+fn capture_on_hotkey(
+    keys: Res<ButtonInput<KeyCode>>,
+    settings: Res<PngCaptureSettings>,
+    mut captures: EventWriter<CapturePng>,
+) {
+    let Some(hotkey) = settings.hotkey else {
+        return;
+    };
+
+    if !keys.just_pressed(hotkey) {
+        return;
+    }
+
+    for (name, target) in &settings.hotkey_targets {
+        captures.send(CapturePng::new(target.clone(), name));
+    }
+}
+
+fn start_captures(
+    mut captures: EventReader<CapturePng>,
+    settings: Res<PngCaptureSettings>,
+    channels: Res<PngCaptureChannels>,
+    primary_window: Query<Entity, With<PrimaryWindow>>,
+    mut screenshot_manager: ResMut<ScreenshotManager>,
+) {
+    for CapturePng { target, name, path } in captures.read() {
+        let path = path
+            .clone()
+            .unwrap_or_else(|| timestamped_path(&settings.directory, name));
+
+        match target {
+            CaptureTarget::Image(image) => {
+                let request = ImageCaptureRequest {
+                    image: image.id(),
+                    path,
+                };
+                channels
+                    .image_requests
+                    .lock()
+                    .expect("nobody should panic while holding the lock")
+                    .push(request);
+            }
+            CaptureTarget::Window(window) => {
+                let Some(window) = window.normalize(primary_window.get_single().ok()) else {
+                    warn!("No window to capture for {name}");
+                    continue;
+                };
+
+                let saved = channels.saved.clone();
+                let screenshot_path = path.clone();
+                let result = screenshot_manager.take_screenshot(window.entity(), move |image| {
+                    save_png(image, screenshot_path, &saved);
+                });
+
+                if let Err(e) = result {
+                    warn!("Could not capture {name}: {e}");
+                    let _ = channels.saved.send(CaptureResult { path, saved: false });
+                }
+            }
+        }
+    }
+}
+
+fn send_saved_events(
+    channels: Res<PngCaptureChannels>,
+    mut saved: EventWriter<PngSaved>,
+    mut failed: EventWriter<PngCaptureFailed>,
+) {
+    let receiver = channels
+        .saved_receiver
+        .lock()
+        .expect("nobody should panic while holding the lock");
+
+    for CaptureResult { path, saved: ok } in receiver.try_iter() {
+        if ok {
+            saved.send(PngSaved { path });
+        } else {
+            failed.send(PngCaptureFailed { path });
+        }
+    }
+}
+
+/// E.g. `captures/window-2024-04-21_19-30-12.345.png`
+fn timestamped_path(directory: &Path, name: &str) -> PathBuf {
+    let timestamp = chrono::Local::now().format("%Y-%m-%d_%H-%M-%S%.3f");
+    directory.join(format!("{name}-{timestamp}.png"))
+}
+
+/// Encodes and writes the file, to be called off the main thread
+fn save_png(image: Image, path: PathBuf, saved: &Sender<CaptureResult>) {
+    let ok = match write_png(image, &path) {
+        Ok(()) => {
+            debug!("Saved {}", path.display());
+            true
+        }
+        Err(e) => {
+            error!("Could not save {}: {e}", path.display());
+            false
+        }
+    };
+
+    // Only fails if the app is gone, at which point nobody is interested anyway
+    let _ = saved.send(CaptureResult { path, saved: ok });
+}
+
+fn write_png(image: Image, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let image = into_rgba8(image)?;
+
+    if let Some(directory) = path.parent() {
+        std::fs::create_dir_all(directory)?;
+    }
+
+    image.save_with_format(path, image::ImageFormat::Png)?;
+    Ok(())
+}
+
+/// Formats [`into_rgba8`] can convert
+fn can_encode(format: TextureFormat) -> bool {
+    matches!(
+        format,
+        TextureFormat::R8Unorm
+            | TextureFormat::Rg8Unorm
+            | TextureFormat::Rgba8UnormSrgb
+            | TextureFormat::Bgra8UnormSrgb
+            | TextureFormat::Bgra8Unorm
+            | TextureFormat::Rgba8Unorm
+            | TextureFormat::Rgba16Float
+            | TextureFormat::Rgba32Float
+    )
+}
+
+/// Like [`Image::try_into_dynamic`], which only knows 8 bit formats stored as they're displayed,
+/// but also converts linear RGBA formats to sRGB
+fn into_rgba8(image: Image) -> Result<DynamicImage, String> {
+    let linear: Vec<f32> = match image.texture_descriptor.format {
+        TextureFormat::Rgba8Unorm => image
+            .data
+            .iter()
+            .map(|&c| c as f32 / u8::MAX as f32)
+            .collect(),
+        TextureFormat::Rgba16Float => image
+            .data
+            .chunks_exact(2)
+            .map(|c| half::f16::from_le_bytes([c[0], c[1]]).to_f32())
+            .collect(),
+        TextureFormat::Rgba32Float => image
+            .data
+            .chunks_exact(4)
+            .map(|c| f32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+            .collect(),
+        _ => return image.try_into_dynamic().map_err(|e| e.to_string()),
+    };
+
+    let data = linear
+        .chunks_exact(4)
+        .flat_map(|c| Color::rgba_linear(c[0], c[1], c[2], c[3]).as_rgba_u8())
+        .collect();
+
+    RgbaImage::from_raw(image.width(), image.height(), data)
+        .map(DynamicImage::ImageRgba8)
+        .ok_or_else(|| format!("{:?} image data doesn't match its size", image.size()))
+}
+
+fn extract_image_requests(
+    channels: Extract<Res<PngCaptureChannels>>,
+    mut requests: ResMut<ImageCaptureRequests>,
+) {
+    requests.requests.append(
+        &mut channels
+            .image_requests
+            .lock()
+            .expect("nobody should panic while holding the lock"),
+    );
+}
+
+fn copy_images_to_buffers(
+    mut requests: ResMut<ImageCaptureRequests>,
+    images: Res<RenderAssets<Image>>,
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+) {
+    let saved = requests.saved.clone();
+
+    for ImageCaptureRequest { image, path } in requests.requests.drain(..) {
+        let Some(gpu_image) = images.get(image) else {
+            warn!("Image for {} isn't on the GPU (yet)", path.display());
+            let _ = saved.send(CaptureResult { path, saved: false });
+            continue;
+        };
+
+        let format = gpu_image.texture_format;
+        if !can_encode(format) {
+            warn!(
+                "Can't save {format:?} images like {} as PNG",
+                path.display()
+            );
+            let _ = saved.send(CaptureResult { path, saved: false });
+            continue;
+        }
+
+        let size = Extent3d {
+            width: gpu_image.size.x as u32,
+            height: gpu_image.size.y as u32,
+            depth_or_array_layers: 1,
+        };
+
+        // Buffer rows must be aligned, which we undo after reading back
+        let row_bytes = size.width * format.pixel_size() as u32;
+        let padded_row_bytes =
+            row_bytes.div_ceil(COPY_BYTES_PER_ROW_ALIGNMENT) * COPY_BYTES_PER_ROW_ALIGNMENT;
+
+        let buffer = render_device.create_buffer(&BufferDescriptor {
+            label: Some("png_capture_buffer"),
+            size: (padded_row_bytes * size.height) as u64,
+            usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = render_device.create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("png_capture_encoder"),
+        });
+        encoder.copy_texture_to_buffer(
+            gpu_image.texture.as_image_copy(),
+            ImageCopyBuffer {
+                buffer: &buffer,
+                layout: ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_row_bytes),
+                    rows_per_image: None,
+                },
+            },
+            size,
+        );
+        render_queue.submit([encoder.finish()]);
+
+        let saved = saved.clone();
+        AsyncComputeTaskPool::get()
+            .spawn(async move {
+                let (mapped_sender, mapped_receiver) = async_channel::bounded(1);
+                let slice = buffer.slice(..);
+
+                // The map is polled every frame when the render queue is submitted
+                slice.map_async(MapMode::Read, move |result| {
+                    let _ = mapped_sender.try_send(result);
+                });
+
+                match mapped_receiver.recv().await {
+                    Ok(Ok(())) => {}
+                    Ok(Err(e)) => {
+                        error!("Could not read back {}: {e}", path.display());
+                        let _ = saved.send(CaptureResult { path, saved: false });
+                        return;
+                    }
+                    Err(_) => return,
+                }
+
+                let data: Vec<u8> = slice
+                    .get_mapped_range()
+                    .chunks_exact(padded_row_bytes as usize)
+                    .flat_map(|row| &row[..row_bytes as usize])
+                    .copied()
+                    .collect();
+                buffer.unmap();
+
+                let image = Image::new(
+                    size,
+                    TextureDimension::D2,
+                    data,
+                    format,
+                    RenderAssetUsages::MAIN_WORLD,
+                );
+                save_png(image, path, &saved);
+            })
+            .detach();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image(format: TextureFormat, pixel: Vec<u8>) -> Image {
+        let size = Extent3d {
+            width: 2,
+            height: 1,
+            depth_or_array_layers: 1,
+        };
+        Image::new(
+            size,
+            TextureDimension::D2,
+            pixel.repeat(2),
+            format,
+            RenderAssetUsages::MAIN_WORLD,
+        )
+    }
+
+    #[test]
+    fn linear_formats_become_srgb() {
+        let color = Color::rgba(1.0, 0.5, 0.0, 1.0);
+        let expected = color.as_rgba_u8();
+
+        let linear = color.as_linear_rgba_f32();
+        let pixels = [
+            (
+                TextureFormat::Rgba16Float,
+                linear
+                    .iter()
+                    .flat_map(|&c| half::f16::from_f32(c).to_le_bytes())
+                    .collect(),
+            ),
+            (
+                TextureFormat::Rgba32Float,
+                linear.iter().flat_map(|c| c.to_le_bytes()).collect(),
+            ),
+            (TextureFormat::Rgba8UnormSrgb, expected.to_vec()),
+        ];
+
+        for (format, pixel) in pixels {
+            let converted = into_rgba8(image(format, pixel)).unwrap().to_rgba8();
+            for channel in converted.pixels().flat_map(|p| p.0).zip(expected.repeat(2)) {
+                let (actual, expected) = channel;
+                assert!(actual.abs_diff(expected) <= 1, "{format:?}: {channel:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn hdr_values_are_clamped() {
+        let pixel = [4.0f32, -1.0, 0.0, 1.0]
+            .iter()
+            .flat_map(|c| c.to_le_bytes())
+            .collect();
+        let converted = into_rgba8(image(TextureFormat::Rgba32Float, pixel))
+            .unwrap()
+            .to_rgba8();
+        assert_eq!(converted.get_pixel(1, 0).0, [255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn unsupported_formats_fail() {
+        assert!(!can_encode(TextureFormat::Rg11b10Float));
+        let pixel = vec![0; 4];
+        assert!(into_rgba8(image(TextureFormat::Rg11b10Float, pixel)).is_err());
+    }
+}
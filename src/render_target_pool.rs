@@ -0,0 +1,432 @@
+use bevy::{
+    diagnostic::{Diagnostic, DiagnosticPath, Diagnostics, RegisterDiagnostic},
+    prelude::*,
+    render::{
+        render_resource::{TextureFormat, TextureUsages},
+        texture::TextureFormatPixelInfo,
+    },
+    utils::HashMap,
+};
+
+use crate::{
+    mip_generation::GenerateMips,
+    render_util::{MipmappedRenderTarget, RenderTargetBuilder},
+};
+
+/// Lends out render targets instead of every user allocating their own and keeping it forever.
+///
+/// Lend one with [`RenderTargetPool::lend`] (or [`RenderTargetPool::lend_with_mips`]) and hand it
+/// back with [`RenderTargetPool::release`],
+/// or put a [`PooledRenderTarget`] on whatever uses it (e.g. the camera) and it's released when
+/// that is despawned.
+/// Released targets are reused by later requests with the same [`RenderTargetKey`], and freed
+/// after [`RenderTargetPool::max_idle_frames`] frames of nobody needing them.
+///
+/// Memory use is published as diagnostics, e.g. for
+/// [`LogDiagnosticsPlugin`](bevy::diagnostic::LogDiagnosticsPlugin), and via
+/// [`RenderTargetPool::memory_report`].
+pub struct RenderTargetPoolPlugin;
+
+impl Plugin for RenderTargetPoolPlugin {
+    fn build(&self, app: &mut App) {
+        for path in [
+            RenderTargetPool::IN_USE_BYTES,
+            RenderTargetPool::IDLE_BYTES,
+            RenderTargetPool::IN_USE_COUNT,
+            RenderTargetPool::IDLE_COUNT,
+        ] {
+            app.register_diagnostic(Diagnostic::new(path));
+        }
+
+        app.init_resource::<RenderTargetPool>().add_systems(
+            Last,
+            (
+                track_pooled_components,
+                release_removed_components,
+                stop_idle_mips,
+                free_idle,
+                measure_pool,
+            )
+                .chain(),
+        );
+    }
+}
+
+/// Targets are only shared between requests that agree on all of these
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RenderTargetKey {
+    pub size: UVec2,
+    pub format: TextureFormat,
+    pub usage: TextureUsages,
+    /// Of the mipmapped copy, for [`RenderTargetPool::lend_with_mips`]
+    pub mip_levels: Option<u32>,
+}
+
+impl RenderTargetKey {
+    /// Including the mipmapped copy, if there is one
+    pub fn bytes(&self) -> usize {
+        let pixels =
+            |mip: u32| ((self.size.x >> mip).max(1) * (self.size.y >> mip).max(1)) as usize;
+
+        let target = pixels(0);
+        let sampled: usize = (0..self.mip_levels.unwrap_or(0)).map(pixels).sum();
+
+        (target + sampled) * self.format.pixel_size()
+    }
+}
+
+/// Releases its render target back to the [`RenderTargetPool`] when removed or despawned
+#[derive(Debug, Component, Deref)]
+pub struct PooledRenderTarget(pub Handle<Image>);
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RenderTargetPoolReport {
+    pub in_use: usize,
+    pub in_use_bytes: usize,
+    pub idle: usize,
+    pub idle_bytes: usize,
+}
+
+#[derive(Debug, Resource)]
+pub struct RenderTargetPool {
+    /// How long a released target is kept around for reuse before it's freed
+    pub max_idle_frames: u32,
+
+    /// By the id of the image rendered to
+    in_use: HashMap<AssetId<Image>, PooledImages>,
+    idle: HashMap<RenderTargetKey, Vec<IdleTarget>>,
+
+    /// Which target each [`PooledRenderTarget`] holds, since it's gone by the time it's removed
+    owners: HashMap<Entity, AssetId<Image>>,
+
+    /// Mipmapped images released since mips were last generated, which don't need them anymore
+    stopped_mips: Vec<Handle<Image>>,
+}
+
+#[derive(Debug)]
+struct PooledImages {
+    key: RenderTargetKey,
+    target: Handle<Image>,
+    /// The mipmapped copy of [`Self::target`], if any
+    sampled: Option<Handle<Image>>,
+}
+
+#[derive(Debug)]
+struct IdleTarget {
+    images: PooledImages,
+    idle_frames: u32,
+}
+
+impl Default for RenderTargetPool {
+    fn default() -> Self {
+        Self {
+            max_idle_frames: 120,
+            in_use: default(),
+            idle: default(),
+            owners: default(),
+            stopped_mips: default(),
+        }
+    }
+}
+
+impl RenderTargetPool {
+    pub const IN_USE_BYTES: DiagnosticPath =
+        DiagnosticPath::const_new("render_target_pool/in_use_bytes");
+    pub const IDLE_BYTES: DiagnosticPath =
+        DiagnosticPath::const_new("render_target_pool/idle_bytes");
+    pub const IN_USE_COUNT: DiagnosticPath =
+        DiagnosticPath::const_new("render_target_pool/in_use_count");
+    pub const IDLE_COUNT: DiagnosticPath =
+        DiagnosticPath::const_new("render_target_pool/idle_count");
+
+    /// Reuses an idle target matching the builder if there is one, or builds a new one.
+    ///
+    /// A reused target still contains whatever was last rendered to it, not the builder's clear
+    /// color. Cameras clear it anyway.
+    pub fn lend(
+        &mut self,
+        builder: RenderTargetBuilder,
+        images: &mut Assets<Image>,
+    ) -> Handle<Image> {
+        let key = builder.key();
+
+        let images = match self.reuse(key) {
+            Some(images) => images,
+            None => {
+                debug!("New pooled render target {key:?}");
+                PooledImages {
+                    key,
+                    target: builder.build(images),
+                    sampled: None,
+                }
+            }
+        };
+
+        let target = images.target.clone();
+        self.in_use.insert(target.id(), images);
+        target
+    }
+
+    /// Like [`RenderTargetBuilder::build_with_mips`], reusing an idle pair if there is one.
+    /// Release it by [`MipmappedRenderTarget::target`].
+    pub fn lend_with_mips(
+        &mut self,
+        builder: RenderTargetBuilder,
+        mip_levels: u32,
+        images: &mut Assets<Image>,
+        generate_mips: &mut GenerateMips,
+    ) -> MipmappedRenderTarget {
+        let key = RenderTargetKey {
+            mip_levels: Some(builder.clamp_mip_levels(mip_levels)),
+            ..builder.key()
+        };
+
+        let target = match self.reuse(key) {
+            Some(PooledImages {
+                target,
+                sampled: Some(sampled),
+                ..
+            }) => {
+                // Still generating mips if it was released this frame
+                self.stopped_mips
+                    .retain(|stopped| stopped.id() != sampled.id());
+                generate_mips.remove(&sampled);
+                generate_mips.add(target.clone_weak(), sampled.clone_weak());
+                MipmappedRenderTarget { target, sampled }
+            }
+            _ => {
+                debug!("New pooled render target {key:?}");
+                builder.build_with_mips(mip_levels, images, generate_mips)
+            }
+        };
+
+        self.in_use.insert(
+            target.target.id(),
+            PooledImages {
+                key,
+                target: target.target.clone(),
+                sampled: Some(target.sampled.clone()),
+            },
+        );
+        target
+    }
+
+    /// Hands a target back for reuse. Releasing something that isn't lent out does nothing.
+    pub fn release(&mut self, image: impl Into<AssetId<Image>>) {
+        let Some(images) = self.in_use.remove(&image.into()) else {
+            return;
+        };
+
+        if let Some(sampled) = &images.sampled {
+            self.stopped_mips.push(sampled.clone_weak());
+        }
+
+        self.idle.entry(images.key).or_default().push(IdleTarget {
+            images,
+            idle_frames: 0,
+        });
+    }
+
+    fn reuse(&mut self, key: RenderTargetKey) -> Option<PooledImages> {
+        self.idle
+            .get_mut(&key)
+            .and_then(Vec::pop)
+            .map(|idle| idle.images)
+    }
+
+    /// Counts a frame of idling, and drops targets that have idled for too long
+    fn free_idle(&mut self) {
+        let max_idle_frames = self.max_idle_frames;
+
+        self.idle.retain(|key, idle| {
+            idle.retain_mut(|target| {
+                target.idle_frames += 1;
+
+                let keep = target.idle_frames <= max_idle_frames;
+                if !keep {
+                    debug!("Freeing pooled render target {key:?}");
+                }
+                keep
+            });
+
+            !idle.is_empty()
+        });
+    }
+
+    pub fn memory_report(&self) -> RenderTargetPoolReport {
+        let mut report = RenderTargetPoolReport::default();
+
+        for images in self.in_use.values() {
+            report.in_use += 1;
+            report.in_use_bytes += images.key.bytes();
+        }
+
+        for (key, idle) in &self.idle {
+            report.idle += idle.len();
+            report.idle_bytes += idle.len() * key.bytes();
+        }
+
+        report
+    }
+}
+
+fn track_pooled_components(
+    mut pool: ResMut<RenderTargetPool>,
+    added: Query<(Entity, &PooledRenderTarget), Changed<PooledRenderTarget>>,
+) {
+    for (entity, pooled) in &added {
+        if let Some(previous) = pool.owners.insert(entity, pooled.id()) {
+            if previous != pooled.id() {
+                pool.release(previous);
+            }
+        }
+    }
+}
+
+fn release_removed_components(
+    mut pool: ResMut<RenderTargetPool>,
+    mut removed: RemovedComponents<PooledRenderTarget>,
+) {
+    for entity in removed.read() {
+        if let Some(image) = pool.owners.remove(&entity) {
+            pool.release(image);
+        }
+    }
+}
+
+/// Idle targets don't need their mips generated
+fn stop_idle_mips(mut pool: ResMut<RenderTargetPool>, generate_mips: Option<ResMut<GenerateMips>>) {
+    let stopped = std::mem::take(&mut pool.stopped_mips);
+
+    if let Some(mut generate_mips) = generate_mips {
+        for sampled in &stopped {
+            generate_mips.remove(sampled);
+        }
+    }
+}
+
+/// Drops the pool's handle to long idle targets, which frees them on the GPU as well
+fn free_idle(mut pool: ResMut<RenderTargetPool>) {
+    pool.free_idle();
+}
+
+fn measure_pool(pool: Res<RenderTargetPool>, mut diagnostics: Diagnostics) {
+    let report = pool.memory_report();
+
+    diagnostics.add_measurement(&RenderTargetPool::IN_USE_BYTES, || {
+        report.in_use_bytes as f64
+    });
+    diagnostics.add_measurement(&RenderTargetPool::IDLE_BYTES, || report.idle_bytes as f64);
+    diagnostics.add_measurement(&RenderTargetPool::IN_USE_COUNT, || report.in_use as f64);
+    diagnostics.add_measurement(&RenderTargetPool::IDLE_COUNT, || report.idle as f64);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn builder(size: u32) -> RenderTargetBuilder {
+        RenderTargetBuilder::new(UVec2::splat(size))
+    }
+
+    #[test]
+    fn reuses_released_targets_with_the_same_key() {
+        let mut images = Assets::<Image>::default();
+        let mut pool = RenderTargetPool::default();
+
+        let first = pool.lend(builder(64), &mut images);
+        pool.release(&first);
+        let second = pool.lend(builder(64), &mut images);
+        assert_eq!(first, second);
+
+        // Still in use, and a different size
+        assert_ne!(pool.lend(builder(64), &mut images), first);
+        assert_ne!(pool.lend(builder(32), &mut images), first);
+        assert_ne!(pool.lend(builder(64).readback(), &mut images), first);
+    }
+
+    #[test]
+    fn mipmapped_targets_only_reuse_their_own_kind() {
+        let mut images = Assets::<Image>::default();
+        let mut generate_mips = GenerateMips::default();
+        let mut pool = RenderTargetPool::default();
+
+        let plain = pool.lend(builder(64), &mut images);
+        pool.release(&plain);
+        let mipmapped = pool.lend_with_mips(builder(64), 4, &mut images, &mut generate_mips);
+        assert_ne!(mipmapped.target, plain);
+
+        pool.release(&mipmapped.target);
+        let again = pool.lend_with_mips(builder(64), 4, &mut images, &mut generate_mips);
+        assert_eq!(again.target, mipmapped.target);
+        assert_eq!(again.sampled, mipmapped.sampled);
+        assert!(pool.stopped_mips.is_empty());
+    }
+
+    #[test]
+    fn frees_targets_idle_for_too_long() {
+        let mut images = Assets::<Image>::default();
+        let mut pool = RenderTargetPool {
+            max_idle_frames: 2,
+            ..default()
+        };
+
+        let image = pool.lend(builder(64), &mut images);
+        pool.release(&image);
+        pool.free_idle();
+        pool.free_idle();
+        assert_eq!(pool.memory_report().idle, 1);
+
+        pool.free_idle();
+        let report = pool.memory_report();
+        assert_eq!(report.idle, 0);
+        assert_eq!(report.idle_bytes, 0);
+        assert_ne!(pool.lend(builder(64), &mut images), image);
+    }
+
+    #[test]
+    fn reports_memory() {
+        let mut images = Assets::<Image>::default();
+        let mut pool = RenderTargetPool::default();
+
+        let image = pool.lend(builder(64), &mut images);
+        pool.lend(builder(64), &mut images);
+        pool.release(&image);
+        // Releasing twice, or something that isn't lent out, does nothing
+        pool.release(&image);
+        pool.release(Handle::<Image>::default().id());
+
+        let report = pool.memory_report();
+        assert_eq!(report.in_use, 1);
+        assert_eq!(report.in_use_bytes, 64 * 64 * 4);
+        assert_eq!(report.idle, 1);
+        assert_eq!(report.idle_bytes, 64 * 64 * 4);
+    }
+
+    #[test]
+    fn releases_when_the_owner_is_despawned() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, RenderTargetPoolPlugin));
+
+        let mut images = Assets::<Image>::default();
+        let image = app
+            .world
+            .resource_mut::<RenderTargetPool>()
+            .lend(builder(64), &mut images);
+        let owner = app.world.spawn(PooledRenderTarget(image)).id();
+        app.update();
+        assert_eq!(
+            app.world
+                .resource::<RenderTargetPool>()
+                .memory_report()
+                .in_use,
+            1
+        );
+
+        app.world.despawn(owner);
+        app.update();
+        let report = app.world.resource::<RenderTargetPool>().memory_report();
+        assert_eq!(report.in_use, 0);
+        assert_eq!(report.idle, 1);
+    }
+}
@@ -1,36 +1,213 @@
 use bevy::{
     prelude::*,
-    render::render_resource::{
-        Extent3d, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages,
+    render::{
+        render_resource::{
+            Extent3d, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages,
+        },
+        texture::TextureFormatPixelInfo,
     },
 };
 
+use crate::{
+    mip_generation::GenerateMips, multisampling::MultisampledImages,
+    render_target_pool::RenderTargetKey,
+};
+
+/// Makes a plain `Bgra8UnormSrgb` image which can be rendered to and sampled.
+/// See [`RenderTargetBuilder`] for more options, and
+/// [`crate::render_target_pool::RenderTargetPool`] for targets that aren't needed forever.
 pub fn make_image(size: UVec2, images: &mut Assets<Image>) -> Handle<Image> {
-    let size = Extent3d {
-        width: size.x,
-        height: size.y,
-        ..default()
-    };
+    RenderTargetBuilder::new(size).build(images)
+}
 
-    // This is the texture that will be rendered to.
-    let mut image = Image {
-        texture_descriptor: TextureDescriptor {
-            label: None,
+/// Builds images which cameras can render to.
+///
+/// Images are always single sampled. Cameras render multisampled according to the [`Msaa`]
+/// resource and resolve into them, custom passes can get a multisampled texture to resolve from
+/// with [`Self::build_multisampled`].
+#[derive(Debug, Clone)]
+pub struct RenderTargetBuilder {
+    size: UVec2,
+    format: TextureFormat,
+    label: Option<&'static str>,
+    usage: TextureUsages,
+    clear_color: Color,
+}
+
+/// A render target with a mip chain, see [`RenderTargetBuilder::build_with_mips`]
+#[derive(Debug, Clone)]
+pub struct MipmappedRenderTarget {
+    /// Point the camera here, e.g. via [`bevy::render::camera::RenderTarget::Image`]
+    pub target: Handle<Image>,
+
+    /// Sample from this, it has mips generated from [`Self::target`] every frame
+    pub sampled: Handle<Image>,
+}
+
+impl RenderTargetBuilder {
+    pub fn new(size: UVec2) -> Self {
+        Self {
             size,
-            dimension: TextureDimension::D2,
             format: TextureFormat::Bgra8UnormSrgb,
-            mip_level_count: 1,
-            sample_count: 1,
+            label: None,
             usage: TextureUsages::TEXTURE_BINDING
                 | TextureUsages::COPY_DST
                 | TextureUsages::RENDER_ATTACHMENT,
-            view_formats: &[],
-        },
-        ..default()
-    };
+            clear_color: Color::NONE,
+        }
+    }
+
+    /// E.g. [`TextureFormat::Rgba16Float`] for HDR
+    pub fn format(mut self, format: TextureFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Shows up in GPU debuggers and wgpu errors
+    pub fn label(mut self, label: &'static str) -> Self {
+        self.label = Some(label);
+        self
+    }
+
+    /// Usages on top of the ones needed to render to and sample the image
+    pub fn usage(mut self, usage: TextureUsages) -> Self {
+        self.usage |= usage;
+        self
+    }
 
-    // fill image.data with zeroes
-    image.resize(size);
+    /// Allow copying the image back to the CPU
+    pub fn readback(self) -> Self {
+        self.usage(TextureUsages::COPY_SRC)
+    }
+
+    /// What the image contains before anything has rendered to it.
+    /// Only supported for the common 8 bit, 16 and 32 bit float RGBA formats, others start zeroed.
+    pub fn clear_color(mut self, color: Color) -> Self {
+        self.clear_color = color;
+        self
+    }
+
+    pub fn key(&self) -> RenderTargetKey {
+        RenderTargetKey {
+            size: self.size,
+            format: self.format,
+            usage: self.usage,
+            mip_levels: None,
+        }
+    }
+
+    /// How many mip levels [`Self::build_with_mips`] makes when asked for `mip_levels`
+    pub fn clamp_mip_levels(&self, mip_levels: u32) -> u32 {
+        // A zero sized target has no mips to speak of, but still one level
+        let max_mip_levels = 32 - self.size.max_element().leading_zeros();
+        mip_levels.min(max_mip_levels).max(1)
+    }
+
+    pub fn build(self, images: &mut Assets<Image>) -> Handle<Image> {
+        images.add(self.image(1, self.usage))
+    }
+
+    /// Builds a target to render into, and an image which gets the target copied into its top mip
+    /// and the rest of the mip chain generated from it, every frame.
+    ///
+    /// A camera can't render into an image with several mips directly, hence two images.
+    /// The mips are generated by [`crate::mip_generation::MipGenerationPlugin`].
+    pub fn build_with_mips(
+        self,
+        mip_levels: u32,
+        images: &mut Assets<Image>,
+        generate_mips: &mut GenerateMips,
+    ) -> MipmappedRenderTarget {
+        let mip_levels = self.clamp_mip_levels(mip_levels);
+
+        let target = images.add(self.image(1, self.usage | TextureUsages::COPY_SRC));
+        let sampled = images.add(self.image(mip_levels, self.usage | TextureUsages::COPY_DST));
+
+        generate_mips.add(target.clone_weak(), sampled.clone_weak());
+
+        MipmappedRenderTarget { target, sampled }
+    }
+
+    /// Builds the image, and has [`crate::multisampling::MultisamplingPlugin`] give it a
+    /// texture with `sample_count` samples per pixel in the render world, to render into and
+    /// resolve into the image. See [`crate::multisampling::MultisampledTextures`].
+    ///
+    /// The sample count must be 1, 2, 4, 8 or 16, as far as the format and GPU support it.
+    /// With 1 it's just [`Self::build`].
+    pub fn build_multisampled(
+        self,
+        sample_count: u32,
+        images: &mut Assets<Image>,
+        multisampled: &mut MultisampledImages,
+    ) -> Result<Handle<Image>, String> {
+        if !matches!(sample_count, 1 | 2 | 4 | 8 | 16) {
+            return Err(format!(
+                "{sample_count} isn't a valid sample count, use 1, 2, 4, 8 or 16"
+            ));
+        }
+
+        let image = self.build(images);
+        if sample_count > 1 {
+            multisampled.add(&image, sample_count);
+        }
+        Ok(image)
+    }
+
+    fn image(&self, mip_levels: u32, usage: TextureUsages) -> Image {
+        let size = Extent3d {
+            width: self.size.x,
+            height: self.size.y,
+            ..default()
+        };
+
+        // Initial contents for every pixel of every mip
+        let pixel = pixel_bytes(self.format, self.clear_color)
+            .unwrap_or_else(|| vec![0; self.format.pixel_size()]);
+        let num_pixels: usize = (0..mip_levels)
+            .map(|mip| size.mip_level_size(mip, TextureDimension::D2))
+            .map(|mip_size| (mip_size.width * mip_size.height) as usize)
+            .sum();
+
+        Image {
+            data: pixel.repeat(num_pixels),
+            texture_descriptor: TextureDescriptor {
+                label: self.label,
+                size,
+                dimension: TextureDimension::D2,
+                format: self.format,
+                mip_level_count: mip_levels,
+                sample_count: 1,
+                usage,
+                view_formats: &[],
+            },
+            ..default()
+        }
+    }
+}
+
+fn pixel_bytes(format: TextureFormat, color: Color) -> Option<Vec<u8>> {
+    let pixel = match format {
+        TextureFormat::Rgba8UnormSrgb => color.as_rgba_u8().to_vec(),
+        TextureFormat::Bgra8UnormSrgb => {
+            let [r, g, b, a] = color.as_rgba_u8();
+            vec![b, g, r, a]
+        }
+        TextureFormat::Rgba8Unorm => color
+            .as_linear_rgba_f32()
+            .map(|c| (c.clamp(0.0, 1.0) * u8::MAX as f32).round() as u8)
+            .to_vec(),
+        TextureFormat::Rgba16Float => color
+            .as_linear_rgba_f32()
+            .iter()
+            .flat_map(|&c| half::f16::from_f32(c).to_le_bytes())
+            .collect(),
+        TextureFormat::Rgba32Float => color
+            .as_linear_rgba_f32()
+            .iter()
+            .flat_map(|c| c.to_le_bytes())
+            .collect(),
+        _ => return None,
+    };
 
-    images.add(image)
+    Some(pixel)
 }
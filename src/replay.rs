@@ -0,0 +1,410 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use bevy::{app::AppExit, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bullet_game::{GameSet, PlayerInput},
+    bullet_pattern::{BulletEmitter, BulletFired},
+    bullets::BulletSet,
+    level::{LevelInput, LevelSettings},
+    simulation::{Simulation, SimulationSet, SimulationState, StateHasher},
+};
+
+/// Records a run of the [`crate::simulation::SimulationPlugin`] to a [`Replay`], or plays one back.
+///
+/// Recording saves on exit. Playing back replaces the [`PlayerInput`] and [`LevelInput`] every
+/// tick and pauses the simulation after the last one, then compares how it ended with how the
+/// recording did. Verifying does the same, but exits, and tells how it went in [`ReplayResult`].
+/// Either way, levels saved meanwhile don't restart their waves, see [`LevelSettings`].
+pub enum ReplayPlugin {
+    Record {
+        path: PathBuf,
+        /// Saved with the replay, e.g. to set up the same run when playing it back
+        settings: BTreeMap<String, String>,
+    },
+    Play(Replay),
+    Verify(Replay),
+}
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<BulletFired>()
+            .insert_resource(LevelSettings {
+                restart_on_reload: false,
+            });
+
+        match self {
+            ReplayPlugin::Record { path, settings } => {
+                app.insert_resource(Recording {
+                    path: path.clone(),
+                    replay: Replay {
+                        settings: settings.clone(),
+                        ..default()
+                    },
+                })
+                .add_systems(
+                    FixedUpdate,
+                    record_tick
+                        .in_set(SimulationSet)
+                        .after(BulletSet::Despawn)
+                        .after(GameSet::Collide),
+                )
+                .add_systems(Last, save_recording);
+            }
+            ReplayPlugin::Play(replay) | ReplayPlugin::Verify(replay) => {
+                if matches!(self, ReplayPlugin::Verify(_)) {
+                    app.init_resource::<ReplayResult>();
+                }
+
+                app.insert_resource(Playback {
+                    replay: replay.clone(),
+                    verify: matches!(self, ReplayPlugin::Verify(_)),
+                    run: 0,
+                    repeated: 0,
+                    diverged: None,
+                    done: false,
+                })
+                .add_systems(
+                    FixedUpdate,
+                    (
+                        finish_playback.before(SimulationSet),
+                        play_input
+                            .in_set(SimulationSet)
+                            .after(GameSet::Input)
+                            .before(GameSet::Player),
+                        check_fired.in_set(SimulationSet).after(BulletSet::Spawn),
+                    ),
+                );
+            }
+        }
+    }
+}
+
+/// A recorded run, saved as RON
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Replay {
+    /// [`Simulation::seed`]
+    pub seed: u64,
+
+    #[serde(default)]
+    pub settings: BTreeMap<String, String>,
+
+    /// Every tick in order, with identical ticks in a row merged
+    pub ticks: Vec<ReplayTicks>,
+
+    /// [`SimulationState::hash`] after the last tick
+    pub hash: u64,
+}
+
+/// The same tick, `repeat` times in a row
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ReplayTicks {
+    pub repeat: u32,
+
+    /// [`PlayerInput`], as RON round trips floats exactly
+    pub movement: [f32; 2],
+    pub focus: bool,
+    pub restart: bool,
+
+    #[serde(default)]
+    pub level: LevelInput,
+
+    /// Hash of the bullets fired, to tell where a playback starts to differ
+    pub fired: u64,
+}
+
+/// Whether a verified replay played out the same, once it's done.
+///
+/// Shared, so it can be read after the app has exited, e.g. for the exit code:
+/// Clone it out of the world before running the app.
+#[derive(Debug, Clone, Default, Resource)]
+pub struct ReplayResult(Arc<Mutex<Option<bool>>>);
+
+impl ReplayResult {
+    /// `None` until the replay is done
+    pub fn matches(&self) -> Option<bool> {
+        *self
+            .0
+            .lock()
+            .expect("nobody should panic while holding the lock")
+    }
+
+    fn set(&self, matches: bool) {
+        *self
+            .0
+            .lock()
+            .expect("nobody should panic while holding the lock") = Some(matches);
+    }
+}
+
+impl Replay {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let ron = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        ron::from_str(&ron).map_err(|e| e.to_string())
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let ron = ron::ser::to_string_pretty(self, default()).map_err(|e| e.to_string())?;
+        if let Some(directory) = path.parent() {
+            std::fs::create_dir_all(directory).map_err(|e| e.to_string())?;
+        }
+        std::fs::write(path, ron).map_err(|e| e.to_string())
+    }
+
+    /// Ticks recorded
+    pub fn len(&self) -> u64 {
+        self.ticks.iter().map(|ticks| ticks.repeat as u64).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ticks.is_empty()
+    }
+
+    fn push(&mut self, tick: ReplayTicks) {
+        match self.ticks.last_mut() {
+            Some(last) if ReplayTicks { repeat: 1, ..*last } == tick => last.repeat += 1,
+            _ => self.ticks.push(tick),
+        }
+    }
+}
+
+#[derive(Debug, Resource)]
+struct Recording {
+    path: PathBuf,
+    replay: Replay,
+}
+
+#[derive(Debug, Resource)]
+struct Playback {
+    replay: Replay,
+    verify: bool,
+
+    /// Index into [`Replay::ticks`]
+    run: usize,
+    /// Ticks of the current run played so far
+    repeated: u32,
+
+    /// First tick which fired differently
+    diverged: Option<u64>,
+    done: bool,
+}
+
+impl Playback {
+    fn current(&self) -> Option<&ReplayTicks> {
+        self.replay.ticks.get(self.run)
+    }
+}
+
+fn record_tick(
+    input: Res<PlayerInput>,
+    level_input: Option<Res<LevelInput>>,
+    mut fired: EventReader<BulletFired>,
+    emitters: Query<&BulletEmitter>,
+    mut recording: ResMut<Recording>,
+) {
+    recording.replay.push(ReplayTicks {
+        repeat: 1,
+        movement: input.movement.to_array(),
+        focus: input.focus,
+        restart: input.restart,
+        level: level_input.map_or_else(default, |input| *input),
+        fired: hash_fired(fired.read(), &emitters),
+    });
+}
+
+/// What was fired, in whatever order it was fired in
+fn hash_fired<'a>(
+    fired: impl Iterator<Item = &'a BulletFired>,
+    emitters: &Query<&BulletEmitter>,
+) -> u64 {
+    let mut shots: Vec<_> = fired
+        .map(|fired| {
+            let seed = emitters
+                .get(fired.emitter)
+                .map_or(0, |emitter| emitter.seed);
+            let [dx, dy] = fired.direction.to_array().map(f32::to_bits);
+            (seed, dx, dy, fired.age.to_bits(), fired.speed)
+        })
+        .collect();
+    shots.sort_unstable_by_key(|&(seed, dx, dy, age, _)| (seed, dx, dy, age));
+
+    let mut hash = StateHasher::default();
+    hash.u64(shots.len() as u64);
+    for (seed, dx, dy, age, speed) in shots {
+        hash.u32(seed);
+        hash.u32(dx);
+        hash.u32(dy);
+        hash.u32(age);
+        hash.speed(speed);
+    }
+    hash.0
+}
+
+fn save_recording(
+    simulation: Res<Simulation>,
+    state: SimulationState,
+    mut recording: ResMut<Recording>,
+    exit: EventReader<AppExit>,
+) {
+    if exit.is_empty() {
+        return;
+    }
+
+    recording.replay.seed = simulation.seed;
+    recording.replay.hash = state.hash();
+
+    let path = &recording.path;
+    match recording.replay.save(path) {
+        Ok(()) => info!(
+            "Saved replay of {} ticks to {}",
+            recording.replay.len(),
+            path.display()
+        ),
+        Err(e) => error!("Could not save replay to {}: {e}", path.display()),
+    }
+}
+
+fn play_input(
+    playback: Res<Playback>,
+    mut input: ResMut<PlayerInput>,
+    level_input: Option<ResMut<LevelInput>>,
+) {
+    let Some(tick) = playback.current() else {
+        return;
+    };
+
+    *input = PlayerInput {
+        movement: Vec2::from_array(tick.movement),
+        focus: tick.focus,
+        restart: tick.restart,
+    };
+    if let Some(mut level_input) = level_input {
+        *level_input = tick.level;
+    }
+}
+
+fn check_fired(
+    simulation: Res<Simulation>,
+    mut fired: EventReader<BulletFired>,
+    emitters: Query<&BulletEmitter>,
+    mut playback: ResMut<Playback>,
+) {
+    let fired = hash_fired(fired.read(), &emitters);
+    let Some(tick) = playback.current().copied() else {
+        return;
+    };
+
+    if tick.fired != fired && playback.diverged.is_none() {
+        warn!(
+            "Replay diverged at tick {}: fired bullets hash to {fired:016x}, {:016x} recorded",
+            simulation.tick, tick.fired
+        );
+        playback.diverged = Some(simulation.tick);
+    }
+
+    playback.repeated += 1;
+    if playback.repeated >= tick.repeat {
+        playback.run += 1;
+        playback.repeated = 0;
+    }
+}
+
+fn finish_playback(
+    mut simulation: ParamSet<(ResMut<Simulation>, SimulationState)>,
+    mut playback: ResMut<Playback>,
+    result: Option<Res<ReplayResult>>,
+    mut exit: EventWriter<AppExit>,
+) {
+    let ticks = playback.replay.len();
+    if playback.done || simulation.p0().tick < ticks {
+        return;
+    }
+
+    playback.done = true;
+    let hash = simulation.p1().hash();
+    simulation.p0().running = false;
+
+    let matches = hash == playback.replay.hash && playback.diverged.is_none();
+    if matches {
+        info!("Replay of {ticks} ticks matches, hash {hash:016x}");
+    } else {
+        error!(
+            "Replay of {ticks} ticks differs, hash {hash:016x} instead of {:016x}",
+            playback.replay.hash
+        );
+    }
+
+    if playback.verify {
+        if let Some(result) = result {
+            result.set(matches);
+        }
+        exit.send(AppExit);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tick(movement: [f32; 2], fired: u64) -> ReplayTicks {
+        ReplayTicks {
+            repeat: 1,
+            movement,
+            focus: false,
+            restart: false,
+            level: default(),
+            fired,
+        }
+    }
+
+    #[test]
+    fn push_merges_runs_of_identical_ticks() {
+        let mut replay = Replay::default();
+        for _ in 0..3 {
+            replay.push(tick([0.0, 0.0], 1));
+        }
+        replay.push(tick([1.0, 0.0], 1));
+        replay.push(tick([1.0, 0.0], 1));
+        replay.push(tick([1.0, 0.0], 2));
+        replay.push(tick([0.0, 0.0], 1));
+
+        let runs: Vec<_> = replay
+            .ticks
+            .iter()
+            .map(|ticks| (ticks.repeat, ticks.movement, ticks.fired))
+            .collect();
+        assert_eq!(
+            runs,
+            [
+                (3, [0.0, 0.0], 1),
+                (2, [1.0, 0.0], 1),
+                (1, [1.0, 0.0], 2),
+                (1, [0.0, 0.0], 1),
+            ]
+        );
+        assert_eq!(replay.len(), 7);
+    }
+
+    #[test]
+    fn push_tells_apart_ticks_differing_in_anything() {
+        let mut replay = Replay::default();
+        replay.push(tick([0.0, 0.0], 0));
+        replay.push(ReplayTicks {
+            focus: true,
+            ..tick([0.0, 0.0], 0)
+        });
+        replay.push(ReplayTicks {
+            restart: true,
+            ..tick([0.0, 0.0], 0)
+        });
+        replay.push(tick([0.0, 0.5], 0));
+
+        assert_eq!(replay.ticks.len(), 4);
+        assert!(replay.ticks.iter().all(|ticks| ticks.repeat == 1));
+    }
+}
@@ -0,0 +1,262 @@
+use bevy::{asset::LoadState, ecs::system::SystemParam, prelude::*};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::{
+    boss::{Boss, BossSet, FollowPath},
+    bullet_behaviors::{Accelerate, AimAtPlayer, Curve, Homing, Split},
+    bullet_game::{BulletGame, GameSet, Player},
+    bullet_pattern::{BulletEmitter, BulletPatternSet, Speed},
+    bullets::{Bullet, BulletSet},
+    level::{LevelRunner, LevelSet},
+};
+
+/// Makes the bullet-hell simulation deterministic: Given the same seed and [`PlayerInput`]
+/// every tick, a run plays out the same, bit for bit, however fast frames come.
+///
+/// Everything simulated runs in [`SimulationSet`] in `FixedUpdate`, which waits until the
+/// patterns of all [`BulletEmitter`]s and any [`SimulationAssets`] are loaded, so slow loading
+/// doesn't shift anything.
+/// Randomness comes from [`SimulationRng`] only, and new emitters get their seed from it.
+///
+/// Presentation, like the camera or bullet rendering, stays in `Update`.
+///
+/// [`PlayerInput`]: crate::bullet_game::PlayerInput
+#[derive(Default)]
+pub struct SimulationPlugin {
+    pub seed: u64,
+}
+
+impl Plugin for SimulationPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Simulation {
+            seed: self.seed,
+            tick: 0,
+            running: false,
+        })
+        .insert_resource(SimulationRng(ChaCha8Rng::seed_from_u64(self.seed)))
+        .init_resource::<SimulationAssets>()
+        .configure_sets(
+            FixedUpdate,
+            (
+                (
+                    GameSet::Input,
+                    GameSet::Player,
+                    LevelSet,
+                    BossSet,
+                    BulletPatternSet,
+                    BulletSet::Spawn,
+                    BulletSet::Steer,
+                    BulletSet::Move,
+                    GameSet::Collide,
+                    BulletSet::Despawn,
+                )
+                    .in_set(SimulationSet),
+                SimulationSet.run_if(|simulation: Res<Simulation>| simulation.running),
+            ),
+        )
+        .add_systems(
+            FixedUpdate,
+            (
+                start_when_loaded.before(SimulationSet),
+                seed_emitters
+                    .in_set(SimulationSet)
+                    .after(BossSet)
+                    .before(BulletPatternSet),
+                advance_tick.after(SimulationSet),
+            ),
+        );
+    }
+}
+
+/// Everything simulated, only runs while [`Simulation::running`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, SystemSet)]
+pub struct SimulationSet;
+
+#[derive(Debug, Clone, Resource)]
+pub struct Simulation {
+    pub seed: u64,
+
+    /// Ticks simulated so far
+    pub tick: u64,
+
+    /// Starts once all emitters' patterns are loaded, set to `false` to pause
+    pub running: bool,
+}
+
+/// The only source of randomness in the simulation
+#[derive(Debug, Clone, Resource, Deref, DerefMut)]
+pub struct SimulationRng(pub ChaCha8Rng);
+
+/// Assets to load before the simulation starts, e.g. patterns emitters will only fire later
+#[derive(Debug, Default, Resource, Deref, DerefMut)]
+pub struct SimulationAssets(pub Vec<UntypedHandle>);
+
+/// Hashes everything simulated, to tell whether two runs ended up the same
+#[derive(SystemParam)]
+pub struct SimulationState<'w, 's> {
+    simulation: Res<'w, Simulation>,
+    game: Res<'w, BulletGame>,
+    players: Query<'w, 's, &'static Player>,
+    emitters: Query<'w, 's, &'static BulletEmitter>,
+    bosses: Query<'w, 's, &'static Boss>,
+    paths: Query<'w, 's, &'static FollowPath>,
+    levels: Query<'w, 's, &'static LevelRunner>,
+    bullets: Query<'w, 's, (&'static Bullet, HasBehaviors)>,
+}
+
+/// Which behaviors a bullet has
+type HasBehaviors = (
+    Has<Accelerate>,
+    Has<Curve>,
+    Has<Homing>,
+    Has<AimAtPlayer>,
+    Has<Split>,
+);
+
+impl<'w, 's> SimulationState<'w, 's> {
+    pub fn hash(&self) -> u64 {
+        let mut hash = StateHasher::default();
+
+        hash.u64(self.simulation.tick);
+        hash.u64(self.game.score);
+        hash.u32(self.game.lives);
+        hash.u32(self.game.grazes);
+        hash.f32(self.game.invulnerable);
+        hash.f32(self.game.time);
+
+        for player in &self.players {
+            hash.vec2(player.position);
+            hash.vec2(player.velocity);
+        }
+        for emitter in &self.emitters {
+            hash.f32(emitter.elapsed);
+            hash.u32(emitter.seed);
+        }
+        for boss in &self.bosses {
+            hash.f32(boss.health);
+            hash.u64(boss.phase as u64);
+            hash.f32(boss.phase_time);
+        }
+        for follow in &self.paths {
+            hash.f32(follow.elapsed);
+        }
+        for runner in &self.levels {
+            hash.u64(runner.wave as u64);
+            hash.f32(runner.wave_time);
+        }
+
+        // Whatever order the entities happen to be in
+        let mut bullets: Vec<_> = self
+            .bullets
+            .iter()
+            .map(
+                |(bullet, (accelerate, curve, homing, aim_at_player, split))| {
+                    let [x, y] = bullet.position.to_array();
+                    let [dx, dy] = bullet.direction.to_array();
+                    let [x, y, dx, dy, age] = [x, y, dx, dy, bullet.age].map(f32::to_bits);
+                    let [kind, a, b, c] = speed_bits(bullet.speed);
+                    let behaviors = [accelerate, curve, homing, aim_at_player, split]
+                        .iter()
+                        .enumerate()
+                        .fold(0, |bits, (i, &has)| bits | (has as u32) << i);
+                    [x, y, dx, dy, age, kind, a, b, c, behaviors]
+                },
+            )
+            .collect();
+        bullets.sort_unstable();
+
+        hash.u64(bullets.len() as u64);
+        bullets.iter().flatten().for_each(|&bits| hash.u32(bits));
+
+        hash.0
+    }
+}
+
+/// FNV-1a, unlike std's hashers it's the same in every build
+pub(crate) struct StateHasher(pub u64);
+
+impl Default for StateHasher {
+    fn default() -> Self {
+        Self(0xCBF2_9CE4_8422_2325)
+    }
+}
+
+impl StateHasher {
+    pub fn bytes(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = (self.0 ^ byte as u64).wrapping_mul(0x0100_0000_01B3);
+        }
+    }
+
+    pub fn u64(&mut self, value: u64) {
+        self.bytes(&value.to_le_bytes());
+    }
+
+    pub fn u32(&mut self, value: u32) {
+        self.bytes(&value.to_le_bytes());
+    }
+
+    pub fn f32(&mut self, value: f32) {
+        self.u32(value.to_bits());
+    }
+
+    pub fn vec2(&mut self, value: Vec2) {
+        self.f32(value.x);
+        self.f32(value.y);
+    }
+
+    pub fn speed(&mut self, speed: Speed) {
+        for bits in speed_bits(speed) {
+            self.u32(bits);
+        }
+    }
+}
+
+/// Which kind of speed, and its numbers
+fn speed_bits(speed: Speed) -> [u32; 4] {
+    match speed {
+        Speed::Constant(speed) => [0, speed.to_bits(), 0, 0],
+        Speed::Ramp { from, to, over } => [1, from.to_bits(), to.to_bits(), over.to_bits()],
+    }
+}
+
+fn start_when_loaded(
+    mut simulation: ResMut<Simulation>,
+    asset_server: Res<AssetServer>,
+    assets: Res<SimulationAssets>,
+    emitters: Query<&BulletEmitter>,
+) {
+    if simulation.running || simulation.tick > 0 {
+        return;
+    }
+
+    // Failed patterns never fire, no need to wait for them
+    let loaded = emitters
+        .iter()
+        .map(|emitter| emitter.pattern.id().untyped())
+        .chain(assets.iter().map(UntypedHandle::id))
+        .all(|id| {
+            asset_server.is_loaded_with_dependencies(id)
+                || asset_server.get_load_state(id) == Some(LoadState::Failed)
+        });
+    if loaded {
+        info!("Simulation started with seed {}", simulation.seed);
+        simulation.running = true;
+    }
+}
+
+fn seed_emitters(
+    mut rng: ResMut<SimulationRng>,
+    mut emitters: Query<&mut BulletEmitter, Added<BulletEmitter>>,
+) {
+    for mut emitter in &mut emitters {
+        emitter.seed = rng.gen();
+    }
+}
+
+fn advance_tick(mut simulation: ResMut<Simulation>) {
+    if simulation.running {
+        simulation.tick += 1;
+    }
+}
@@ -0,0 +1,83 @@
+use bevy::{
+    prelude::*,
+    render::render_resource::{AsBindGroup, ShaderRef},
+    sprite::{Material2d, Material2dPlugin, MaterialMesh2dBundle, Mesh2dHandle},
+};
+
+use crate::bullets::{Bullet, BulletArena};
+
+/// Draws [`Bullet`]s as round sprites with a [`BulletSpriteMaterial`], for 2D arenas on
+/// [`crate::bullets::ArenaPlane::XY`].
+///
+/// Insert [`BulletSprites`] to start drawing. Each bullet entity gets a [`MaterialMesh2dBundle`]
+/// sharing one mesh and material, so they're batched, and which is removed with everything else
+/// when the bullet is despawned.
+pub struct SpriteBulletsPlugin;
+
+impl Plugin for SpriteBulletsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(Material2dPlugin::<BulletSpriteMaterial>::default())
+            .add_systems(
+                Update,
+                (add_bullet_sprites, place_bullet_sprites)
+                    .chain()
+                    .run_if(resource_exists::<BulletSprites>),
+            );
+    }
+}
+
+/// How bullets look
+#[derive(Debug, Clone, Resource)]
+pub struct BulletSprites {
+    pub mesh: Mesh2dHandle,
+    pub material: Handle<BulletSpriteMaterial>,
+
+    /// Diameter in world units
+    pub size: f32,
+    /// Z, above whatever bullets fly over
+    pub z: f32,
+}
+
+/// Samples `texture` across a circle, over `backdrop` where the texture is transparent
+#[derive(Asset, TypePath, AsBindGroup, Debug, Clone)]
+pub struct BulletSpriteMaterial {
+    #[uniform(0)]
+    pub backdrop: Color,
+
+    #[texture(1)]
+    #[sampler(2)]
+    pub texture: Option<Handle<Image>>,
+}
+
+impl Material2d for BulletSpriteMaterial {
+    fn fragment_shader() -> ShaderRef {
+        "shaders/bullet_sprite.wgsl".into()
+    }
+}
+
+/// New and reused bullets alike, as despawning strips the sprite
+fn add_bullet_sprites(
+    sprites: Res<BulletSprites>,
+    mut commands: Commands,
+    bullets: Query<Entity, (With<Bullet>, Without<Mesh2dHandle>)>,
+) {
+    for bullet in &bullets {
+        commands.entity(bullet).insert(MaterialMesh2dBundle {
+            mesh: sprites.mesh.clone(),
+            material: sprites.material.clone(),
+            transform: Transform::from_scale(Vec3::splat(sprites.size)),
+            ..default()
+        });
+    }
+}
+
+fn place_bullet_sprites(
+    arena: Res<BulletArena>,
+    sprites: Res<BulletSprites>,
+    mut bullets: Query<(&mut Transform, &Bullet)>,
+) {
+    for (mut transform, bullet) in &mut bullets {
+        transform.translation = arena.plane.unproject(bullet.position, sprites.z);
+        transform.scale = Vec3::splat(sprites.size);
+    }
+}
@@ -0,0 +1,322 @@
+use std::time::Duration;
+
+use bevy::{
+    prelude::*,
+    render::camera::RenderTarget,
+    ui::TargetCamera,
+    window::{CursorIcon, PrimaryWindow},
+    winit::WinitWindows,
+};
+use winit::window::ResizeDirection;
+
+/// Lets undecorated windows with [`WindowHandles`] be moved and resized with the mouse.
+///
+/// - Alt + drag anywhere, or drag the handle that shows up when hovering the top edge, to move
+/// - Drag an edge or corner to resize
+/// - Double-click the handle or an edge to toggle a compact size
+///
+/// The window manager does the actual moving and resizing, so it snaps like any other window.
+/// Added by [`crate::default_plugin_extensions::WindowPreset::draggable`], and fine to add again.
+pub struct WindowHandlesPlugin;
+
+impl Plugin for WindowHandlesPlugin {
+    fn build(&self, app: &mut App) {
+        if app.is_plugin_added::<Self>() {
+            return;
+        }
+
+        app.add_systems(
+            Update,
+            (spawn_move_handles, drag_windows, show_move_handles),
+        );
+    }
+
+    fn is_unique(&self) -> bool {
+        false
+    }
+}
+
+#[derive(Debug, Clone, Component)]
+pub struct WindowHandles {
+    /// How close to an edge, in logical pixels, a drag resizes instead of moves
+    pub edge: f32,
+
+    /// Size of the move handle at the top, in logical pixels
+    pub handle_size: Vec2,
+
+    /// Dragging with this held moves the window from anywhere
+    pub move_modifier: Option<KeyCode>,
+
+    /// Logical size to toggle to on double-click
+    pub compact_size: Option<Vec2>,
+}
+
+impl Default for WindowHandles {
+    fn default() -> Self {
+        Self {
+            edge: 8.0,
+            handle_size: Vec2::new(96.0, 12.0),
+            move_modifier: Some(KeyCode::AltLeft),
+            compact_size: Some(Vec2::new(320.0, 180.0)),
+        }
+    }
+}
+
+/// Clicks closer together than this are a double-click
+const DOUBLE_CLICK: Duration = Duration::from_millis(350);
+
+#[derive(Debug, Default, Component)]
+struct WindowHandlesState {
+    last_click: Option<Duration>,
+
+    /// What's under the cursor, whose cursor icon is showing
+    hovered: Option<Drag>,
+    /// The window's own cursor icon, to put back when leaving the handles
+    icon_before: Option<CursorIcon>,
+
+    /// Size before going compact, to go back to
+    expanded_size: Option<Vec2>,
+
+    move_handle: Option<Entity>,
+}
+
+#[derive(Debug, Component)]
+struct MoveHandle {
+    window: Entity,
+}
+
+/// What dragging at a position in the window does
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Drag {
+    Move,
+    Resize(ResizeDirection),
+}
+
+impl WindowHandles {
+    fn drag_at(&self, window: &Window, cursor: Vec2, modifier_held: bool) -> Option<Drag> {
+        let size = Vec2::new(window.width(), window.height());
+
+        let west = cursor.x < self.edge;
+        let east = cursor.x > size.x - self.edge;
+        let north = cursor.y < self.edge;
+        let south = cursor.y > size.y - self.edge;
+
+        let direction = match (north, south, west, east) {
+            (true, _, true, _) => Some(ResizeDirection::NorthWest),
+            (true, _, _, true) => Some(ResizeDirection::NorthEast),
+            (_, true, true, _) => Some(ResizeDirection::SouthWest),
+            (_, true, _, true) => Some(ResizeDirection::SouthEast),
+            (true, ..) => Some(ResizeDirection::North),
+            (_, true, ..) => Some(ResizeDirection::South),
+            (.., true, _) => Some(ResizeDirection::West),
+            (.., true) => Some(ResizeDirection::East),
+            _ => None,
+        };
+
+        if let Some(direction) = direction {
+            return Some(Drag::Resize(direction));
+        }
+
+        if modifier_held || self.handle_rect(window).contains(cursor) {
+            return Some(Drag::Move);
+        }
+
+        None
+    }
+
+    /// Centered at the top, just inside the resize edge
+    fn handle_rect(&self, window: &Window) -> Rect {
+        let center = Vec2::new(window.width() / 2., self.edge + self.handle_size.y / 2.);
+        Rect::from_center_size(center, self.handle_size)
+    }
+
+    /// Hovering this shows the move handle
+    fn hover_rect(&self, window: &Window) -> Rect {
+        Rect::new(
+            0.0,
+            0.0,
+            window.width(),
+            self.edge + self.handle_size.y * 3.,
+        )
+    }
+}
+
+impl Drag {
+    fn cursor_icon(self) -> CursorIcon {
+        match self {
+            Drag::Move => CursorIcon::Move,
+            Drag::Resize(direction) => match direction {
+                ResizeDirection::North => CursorIcon::NResize,
+                ResizeDirection::NorthEast => CursorIcon::NeResize,
+                ResizeDirection::East => CursorIcon::EResize,
+                ResizeDirection::SouthEast => CursorIcon::SeResize,
+                ResizeDirection::South => CursorIcon::SResize,
+                ResizeDirection::SouthWest => CursorIcon::SwResize,
+                ResizeDirection::West => CursorIcon::WResize,
+                ResizeDirection::NorthWest => CursorIcon::NwResize,
+            },
+        }
+    }
+}
+
+/// A UI bar per window, shown on hover. Waits for a camera rendering to the window.
+fn spawn_move_handles(
+    mut commands: Commands,
+    mut windows: Query<(Entity, &WindowHandles, Option<&mut WindowHandlesState>)>,
+    primary_window: Query<Entity, With<PrimaryWindow>>,
+    cameras: Query<(Entity, &Camera)>,
+) {
+    let primary_window = primary_window.get_single().ok();
+
+    for (window, handles, state) in &mut windows {
+        let Some(mut state) = state else {
+            commands
+                .entity(window)
+                .insert(WindowHandlesState::default());
+            continue;
+        };
+
+        if state.move_handle.is_some() {
+            continue;
+        }
+
+        let Some((camera, _)) = cameras
+            .iter()
+            .filter(|(_, camera)| match &camera.target {
+                RenderTarget::Window(target) => {
+                    target.normalize(primary_window).map(|w| w.entity()) == Some(window)
+                }
+                _ => false,
+            })
+            .max_by_key(|(_, camera)| camera.order)
+        else {
+            continue;
+        };
+
+        let handle = commands
+            .spawn((
+                NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        top: Val::Px(handles.edge),
+                        left: Val::Percent(50.),
+                        margin: UiRect::left(Val::Px(-handles.handle_size.x / 2.)),
+                        width: Val::Px(handles.handle_size.x),
+                        height: Val::Px(handles.handle_size.y),
+                        ..default()
+                    },
+                    background_color: Color::rgba(1.0, 1.0, 1.0, 0.6).into(),
+                    visibility: Visibility::Hidden,
+                    ..default()
+                },
+                TargetCamera(camera),
+                MoveHandle { window },
+            ))
+            .id();
+
+        state.move_handle = Some(handle);
+    }
+}
+
+fn show_move_handles(
+    windows: Query<(&Window, &WindowHandles)>,
+    mut move_handles: Query<(&MoveHandle, &mut Visibility)>,
+) {
+    for (move_handle, mut visibility) in &mut move_handles {
+        let Ok((window, handles)) = windows.get(move_handle.window) else {
+            continue;
+        };
+
+        let hovered = window
+            .cursor_position()
+            .is_some_and(|cursor| handles.hover_rect(window).contains(cursor));
+
+        visibility.set_if_neq(if hovered {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        });
+    }
+}
+
+fn drag_windows(
+    time: Res<Time<Real>>,
+    winit_windows: NonSend<WinitWindows>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut windows: Query<(Entity, &mut Window, &WindowHandles, &mut WindowHandlesState)>,
+) {
+    for (entity, mut window, handles, mut state) in &mut windows {
+        let modifier_held = handles.move_modifier.is_some_and(|key| keys.pressed(key));
+        let drag = window
+            .cursor_position()
+            .and_then(|cursor| handles.drag_at(&window, cursor, modifier_held));
+
+        // Only touch the icon on the way in and out, so the app can set its own otherwise
+        if drag != state.hovered {
+            match drag {
+                Some(drag) => {
+                    if state.hovered.is_none() {
+                        state.icon_before = Some(window.cursor.icon);
+                    }
+                    window.cursor.icon = drag.cursor_icon();
+                }
+                None => {
+                    window.cursor.icon = state.icon_before.take().unwrap_or_default();
+                }
+            }
+            state.hovered = drag;
+        }
+
+        if !mouse.just_pressed(MouseButton::Left) {
+            continue;
+        }
+
+        // Clicks elsewhere are the app's
+        let Some(drag) = drag else {
+            state.last_click = None;
+            continue;
+        };
+
+        let now = time.elapsed();
+        let double_click = state
+            .last_click
+            .is_some_and(|last| now - last < DOUBLE_CLICK);
+        state.last_click = Some(now);
+
+        if double_click {
+            state.last_click = None;
+            toggle_compact(&mut window, handles, &mut state);
+            continue;
+        }
+
+        let Some(winit_window) = winit_windows.get_window(entity) else {
+            continue;
+        };
+
+        let result = match drag {
+            Drag::Move => winit_window.drag_window(),
+            Drag::Resize(direction) => winit_window.drag_resize_window(direction),
+        };
+
+        if let Err(e) = result {
+            warn!("Could not drag window: {e}");
+        }
+    }
+}
+
+fn toggle_compact(window: &mut Window, handles: &WindowHandles, state: &mut WindowHandlesState) {
+    let Some(compact_size) = handles.compact_size else {
+        return;
+    };
+
+    let size = match state.expanded_size.take() {
+        Some(expanded_size) => expanded_size,
+        None => {
+            state.expanded_size = Some(Vec2::new(window.width(), window.height()));
+            compact_size
+        }
+    };
+
+    window.resolution.set(size.x, size.y);
+}
@@ -0,0 +1,395 @@
+use std::{collections::BTreeMap, path::PathBuf, time::Duration};
+
+use bevy::{
+    app::AppExit,
+    prelude::*,
+    window::{PrimaryWindow, WindowMode, WindowMoved, WindowResized},
+    winit::WinitWindows,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::overlay_placement::{available_monitors, MonitorRect, OverlayPlacement};
+
+/// Remembers where windows with a [`PersistWindowLayout`] were, and puts them back there on the
+/// next launch.
+///
+/// Layouts are saved to a RON file shortly after windows are moved or resized, and on exit.
+/// A window that would end up off-screen (e.g. its monitor was unplugged) is centered on its old
+/// monitor if still there, or the primary one otherwise.
+///
+/// A restored window loses its [`OverlayPlacement`], which is then only the first launch default.
+/// Hidden windows (e.g. during offline rendering) are neither restored nor saved.
+pub struct WindowLayoutPlugin {
+    /// Defaults to `window-layouts/<binary name>.ron`
+    pub path: PathBuf,
+
+    /// What to call the primary window in the file, or `None` to not persist it
+    pub primary_window: Option<String>,
+}
+
+impl Default for WindowLayoutPlugin {
+    fn default() -> Self {
+        let binary = std::env::current_exe()
+            .ok()
+            .and_then(|exe| {
+                exe.file_stem()
+                    .map(|stem| stem.to_string_lossy().into_owned())
+            })
+            .unwrap_or("streamville".to_owned());
+
+        Self {
+            path: PathBuf::from("window-layouts").join(format!("{binary}.ron")),
+            primary_window: Some("primary".to_owned()),
+        }
+    }
+}
+
+impl Plugin for WindowLayoutPlugin {
+    fn build(&self, app: &mut App) {
+        let layouts = SavedWindowLayouts::load(self.path.clone());
+
+        if let Some(name) = self.primary_window.clone() {
+            let mut primary_window = app.world.query_filtered::<Entity, With<PrimaryWindow>>();
+            if let Ok(window) = primary_window.get_single(&app.world) {
+                app.world
+                    .entity_mut(window)
+                    .insert(PersistWindowLayout::new(name));
+            }
+        }
+
+        app.insert_resource(layouts)
+            // Before overlay placement in `Update`, so a restored window isn't placed as well
+            .add_systems(PreUpdate, restore_layouts)
+            .add_systems(Update, mark_changed_layouts)
+            .add_systems(Last, save_layouts);
+    }
+}
+
+/// Save and restore this window's layout under this name
+#[derive(Debug, Clone, Component)]
+pub struct PersistWindowLayout {
+    pub name: String,
+}
+
+impl PersistWindowLayout {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self { name: name.into() }
+    }
+}
+
+/// In physical pixels, on the virtual desktop
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WindowLayout {
+    pub position: IVec2,
+    pub size: UVec2,
+
+    /// Name of the monitor the window was mostly on
+    pub monitor: Option<String>,
+
+    pub mode: SavedWindowMode,
+}
+
+/// [`WindowMode`], which isn't serializable
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SavedWindowMode {
+    Windowed,
+    BorderlessFullscreen,
+    SizedFullscreen,
+    Fullscreen,
+}
+
+impl From<WindowMode> for SavedWindowMode {
+    fn from(mode: WindowMode) -> Self {
+        match mode {
+            WindowMode::Windowed => Self::Windowed,
+            WindowMode::BorderlessFullscreen => Self::BorderlessFullscreen,
+            WindowMode::SizedFullscreen => Self::SizedFullscreen,
+            WindowMode::Fullscreen => Self::Fullscreen,
+        }
+    }
+}
+
+impl From<SavedWindowMode> for WindowMode {
+    fn from(mode: SavedWindowMode) -> Self {
+        match mode {
+            SavedWindowMode::Windowed => Self::Windowed,
+            SavedWindowMode::BorderlessFullscreen => Self::BorderlessFullscreen,
+            SavedWindowMode::SizedFullscreen => Self::SizedFullscreen,
+            SavedWindowMode::Fullscreen => Self::Fullscreen,
+        }
+    }
+}
+
+/// How much of a window's top left corner must be on a monitor to count as on-screen,
+/// enough to grab it and drag it somewhere else
+const GRABBABLE: IVec2 = IVec2::new(64, 32);
+
+/// Wait this long after the last move or resize before saving, so dragging doesn't write constantly
+const SAVE_DELAY: Duration = Duration::from_millis(500);
+
+impl WindowLayout {
+    /// Where the window should go on these monitors, which may not be where it was saved
+    pub fn fit_to(&self, monitors: &[MonitorRect]) -> (IVec2, UVec2) {
+        let grab_area = IRect::from_corners(self.position, self.position + GRABBABLE);
+        let on_screen = monitors
+            .iter()
+            .any(|monitor| monitor.rect().intersect(grab_area).size() == GRABBABLE);
+
+        if on_screen {
+            return (self.position, self.size);
+        }
+
+        let Some(monitor) = monitors
+            .iter()
+            .find(|monitor| monitor.name.is_some() && monitor.name == self.monitor)
+            .or(monitors.iter().find(|monitor| monitor.primary))
+            .or(monitors.first())
+        else {
+            return (self.position, self.size);
+        };
+
+        warn!(
+            "Saved position {} is off-screen, centering on {:?}",
+            self.position, monitor.name
+        );
+
+        let size = self.size.min(monitor.size);
+        let position = monitor.rect().center() - size.as_ivec2() / 2;
+        (position, size)
+    }
+}
+
+#[derive(Debug, Resource)]
+struct SavedWindowLayouts {
+    path: PathBuf,
+    layouts: BTreeMap<String, WindowLayout>,
+
+    /// Set when something changed, saved once it's been quiet for [`SAVE_DELAY`]
+    unsaved_since: Option<Duration>,
+}
+
+impl SavedWindowLayouts {
+    fn load(path: PathBuf) -> Self {
+        let layouts = match std::fs::read_to_string(&path) {
+            Ok(ron) => ron::from_str(&ron).unwrap_or_else(|e| {
+                warn!("Ignoring broken window layouts in {}: {e}", path.display());
+                default()
+            }),
+            Err(_) => default(),
+        };
+
+        Self {
+            path,
+            layouts,
+            unsaved_since: None,
+        }
+    }
+
+    fn save(&mut self) {
+        self.unsaved_since = None;
+
+        let result = ron::ser::to_string_pretty(&self.layouts, default())
+            .map_err(|e| e.to_string())
+            .and_then(|ron| {
+                if let Some(directory) = self.path.parent() {
+                    std::fs::create_dir_all(directory).map_err(|e| e.to_string())?;
+                }
+                std::fs::write(&self.path, ron).map_err(|e| e.to_string())
+            });
+
+        match result {
+            Ok(()) => debug!("Saved window layouts to {}", self.path.display()),
+            Err(e) => error!(
+                "Could not save window layouts to {}: {e}",
+                self.path.display()
+            ),
+        }
+    }
+}
+
+fn restore_layouts(
+    mut commands: Commands,
+    winit_windows: NonSend<WinitWindows>,
+    layouts: Res<SavedWindowLayouts>,
+    mut windows: Query<(Entity, &mut Window, &PersistWindowLayout)>,
+    mut restored: Local<Vec<Entity>>,
+) {
+    for (entity, mut window, persist) in &mut windows {
+        if restored.contains(&entity) || !window.visible {
+            continue;
+        }
+
+        // Not created yet
+        let Some(monitors) = available_monitors(&winit_windows, entity) else {
+            continue;
+        };
+        restored.push(entity);
+
+        let Some(layout) = layouts.layouts.get(&persist.name) else {
+            continue;
+        };
+
+        let (position, size) = layout.fit_to(&monitors);
+        debug!("Restoring {} to {position} with size {size}", persist.name);
+
+        window.position = WindowPosition::At(position);
+        window.resolution.set_physical_resolution(size.x, size.y);
+        window.mode = layout.mode.into();
+        commands.entity(entity).remove::<OverlayPlacement>();
+    }
+}
+
+fn mark_changed_layouts(
+    time: Res<Time<Real>>,
+    winit_windows: NonSend<WinitWindows>,
+    mut layouts: ResMut<SavedWindowLayouts>,
+    mut moved: EventReader<WindowMoved>,
+    mut resized: EventReader<WindowResized>,
+    windows: Query<(&Window, &PersistWindowLayout)>,
+) {
+    let changed = moved
+        .read()
+        .map(|e| e.window)
+        .chain(resized.read().map(|e| e.window));
+
+    for entity in changed {
+        let Ok((window, persist)) = windows.get(entity) else {
+            continue;
+        };
+        if !window.visible {
+            continue;
+        }
+
+        // Only known once the window has been placed
+        let WindowPosition::At(position) = window.position else {
+            continue;
+        };
+        let size = UVec2::new(
+            window.resolution.physical_width(),
+            window.resolution.physical_height(),
+        );
+
+        let center = position + size.as_ivec2() / 2;
+        let monitor = available_monitors(&winit_windows, entity)
+            .unwrap_or_default()
+            .into_iter()
+            .find(|monitor| monitor.rect().contains(center))
+            .and_then(|monitor| monitor.name);
+
+        let layout = WindowLayout {
+            position,
+            size,
+            monitor,
+            mode: window.mode.into(),
+        };
+
+        if layouts.layouts.get(&persist.name) != Some(&layout) {
+            layouts.layouts.insert(persist.name.clone(), layout);
+            layouts.unsaved_since = Some(time.elapsed());
+        }
+    }
+}
+
+fn save_layouts(
+    time: Res<Time<Real>>,
+    mut layouts: ResMut<SavedWindowLayouts>,
+    exit: EventReader<AppExit>,
+) {
+    let Some(unsaved_since) = layouts.unsaved_since else {
+        return;
+    };
+
+    if !exit.is_empty() || time.elapsed() - unsaved_since > SAVE_DELAY {
+        layouts.save();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn monitor(name: &str, primary: bool, position: IVec2, size: UVec2) -> MonitorRect {
+        MonitorRect {
+            name: Some(name.to_owned()),
+            primary,
+            position,
+            size,
+        }
+    }
+
+    /// A 1080p primary monitor with a 1440p one to its right
+    fn monitors() -> Vec<MonitorRect> {
+        vec![
+            monitor("DP-1", true, IVec2::ZERO, UVec2::new(1920, 1080)),
+            monitor("DP-2", false, IVec2::new(1920, 0), UVec2::new(2560, 1440)),
+        ]
+    }
+
+    fn layout(position: IVec2, size: UVec2, monitor: &str) -> WindowLayout {
+        WindowLayout {
+            position,
+            size,
+            monitor: Some(monitor.to_owned()),
+            mode: SavedWindowMode::Windowed,
+        }
+    }
+
+    #[test]
+    fn keeps_windows_that_can_be_grabbed() {
+        let size = UVec2::new(800, 600);
+
+        // On the second monitor, and hanging off the bottom right of the first
+        for position in [IVec2::new(3000, 500), IVec2::new(1800, 1000)] {
+            let layout = layout(position, size, "DP-1");
+            assert_eq!(layout.fit_to(&monitors()), (position, size));
+        }
+    }
+
+    #[test]
+    fn centers_off_screen_windows_on_their_monitor() {
+        let size = UVec2::new(800, 600);
+
+        // Only partly grabbable, below the first monitor
+        let layout = layout(IVec2::new(100, 1060), size, "DP-2");
+        assert_eq!(
+            layout.fit_to(&monitors()),
+            (IVec2::new(1920 + 1280 - 400, 720 - 300), size)
+        );
+    }
+
+    #[test]
+    fn falls_back_to_the_primary_monitor() {
+        // Saved on a monitor that has since been unplugged
+        let layout = layout(IVec2::new(5000, 0), UVec2::new(800, 600), "HDMI-1");
+        assert_eq!(
+            layout.fit_to(&monitors()),
+            (IVec2::new(960 - 400, 540 - 300), UVec2::new(800, 600))
+        );
+
+        // Nothing's primary, e.g. on Wayland
+        let mut monitors = monitors();
+        monitors.reverse();
+        for monitor in &mut monitors {
+            monitor.primary = false;
+        }
+        assert_eq!(
+            layout.fit_to(&monitors).0,
+            IVec2::new(1920 + 1280 - 400, 720 - 300)
+        );
+    }
+
+    #[test]
+    fn shrinks_windows_larger_than_the_monitor() {
+        let layout = layout(IVec2::new(-4000, 0), UVec2::new(2560, 1440), "DP-1");
+        assert_eq!(
+            layout.fit_to(&monitors()),
+            (IVec2::ZERO, UVec2::new(1920, 1080))
+        );
+    }
+
+    #[test]
+    fn leaves_windows_alone_without_monitors() {
+        let layout = layout(IVec2::new(-4000, 0), UVec2::new(800, 600), "DP-1");
+        assert_eq!(layout.fit_to(&[]), (layout.position, layout.size));
+    }
+}
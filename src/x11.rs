@@ -0,0 +1,54 @@
+//! Talking to the X server directly, for what winit doesn't do on X11
+
+use bevy::{prelude::*, window::RawHandleWrapper};
+#[cfg(target_os = "linux")]
+use raw_window_handle::RawWindowHandle;
+
+/// Our own connection to the X server, `None` when not on X11
+#[cfg(target_os = "linux")]
+#[derive(Resource)]
+pub(crate) struct X11Connection(pub Option<x11rb::rust_connection::RustConnection>);
+
+#[cfg(not(target_os = "linux"))]
+#[derive(Resource)]
+pub(crate) struct X11Connection(pub Option<()>);
+
+impl X11Connection {
+    #[cfg(target_os = "linux")]
+    fn connect() -> Self {
+        match x11rb::connect(None) {
+            Ok((connection, _screen)) => Self(Some(connection)),
+            Err(e) => {
+                info!("No X11 connection ({e}), falling back to what winit can do");
+                Self(None)
+            }
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn connect() -> Self {
+        Self(None)
+    }
+
+    /// Connects once, for all the plugins needing it
+    pub(crate) fn init(app: &mut App) {
+        if !app.world.contains_resource::<Self>() {
+            app.insert_resource(Self::connect());
+        }
+    }
+}
+
+/// The native X11 window, if that's what this is
+#[cfg(target_os = "linux")]
+pub(crate) fn x11_window(handle: &RawHandleWrapper) -> Option<u32> {
+    match handle.window_handle {
+        RawWindowHandle::Xlib(handle) => Some(handle.window as u32),
+        RawWindowHandle::Xcb(handle) => Some(handle.window.get()),
+        _ => None,
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn x11_window(_handle: &RawHandleWrapper) -> Option<u32> {
+    None
+}